@@ -0,0 +1,239 @@
+//! Derive macro for `dubble`.
+//!
+//! Use it through the `derive` feature of `dubble`, rather than depending on
+//! this crate directly.
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::
+{
+    parse_macro_input,
+    Data,
+    DeriveInput,
+    Error,
+    Fields,
+    Ident,
+    LitStr,
+};
+
+/// Generates a "buffered" twin of a struct, in which every field is wrapped
+/// in a `DoubleBuffered`.
+///
+/// For a struct `Foo`, this generates a struct `FooBuffered` with the same
+/// fields (and the same visibility), each of type `DoubleBuffered<_>`. It
+/// has:
+///
+/// - `new(Foo)`, and `From<Foo>`, which initialise both buffers of every field
+/// - `read_<field>()` and `write_<field>()` for each field
+/// - `update()`, which commits every field, and an `Updatable` impl
+/// - `read()`, which clones the read buffers back into a `Foo`
+/// - `unbuffer_read()` and `unbuffer_write()`, like `DoubleBuffered`
+///
+/// Every field type must be `Clone`. The name of the generated struct can be
+/// changed with `#[double_buffer(name = "...")]`.
+///
+/// ```rust
+/// use dubble::DoubleBuffer;
+///
+/// #[derive(DoubleBuffer, Clone, Debug, PartialEq)]
+/// #[double_buffer(name = "PlayerState")]
+/// struct Player
+/// {
+///     hp: i32,
+///     name: String,
+/// }
+///
+/// let mut player = PlayerState::new(Player { hp: 100, name: "Ferris".into() });
+///
+/// *player.write_hp() -= 10;
+/// player.name.push_str(" the Crab");
+/// assert!(*player.read_hp() == 100);
+///
+/// player.update();
+/// assert!(player.read() == Player { hp: 90, name: "Ferris the Crab".into() });
+/// ```
+#[proc_macro_derive(DoubleBuffer, attributes(double_buffer))]
+pub fn derive_double_buffer(input: TokenStream) -> TokenStream
+{
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(input)
+    {
+        Ok(tokens) => tokens.into(),
+        Err(err)   => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream>
+{
+    let name = &input.ident;
+    let vis = &input.vis;
+    let twin = twin_name(&input)?;
+
+    let fields = match input.data
+    {
+        Data::Struct(ref data) => match data.fields
+        {
+            Fields::Named(ref fields) => &fields.named,
+            _ => return Err(Error::new_spanned(
+                &input.ident,
+                "DoubleBuffer can only be derived for structs with named fields",
+            )),
+        },
+
+        _ => return Err(Error::new_spanned(
+            &input.ident,
+            "DoubleBuffer can only be derived for structs",
+        )),
+    };
+
+    let field_vis: Vec<_> = fields.iter().map(|f| &f.vis).collect();
+    let field_names: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let field_types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+
+    let readers: Vec<_> = field_names.iter().map(|f| format_ident!("read_{}", f)).collect();
+    let writers: Vec<_> = field_names.iter().map(|f| format_ident!("write_{}", f)).collect();
+
+    let read_docs: Vec<_> = field_names.iter()
+        .map(|f| format!("Returns an immutable reference to the read buffer of `{}`.", f))
+        .collect();
+    let write_docs: Vec<_> = field_names.iter()
+        .map(|f| format!("Returns a mutable reference to the write buffer of `{}`.", f))
+        .collect();
+    let twin_doc = format!(
+        "A copy of `{}` in which every field is double-buffered. Generated by \
+         `#[derive(DoubleBuffer)]`.",
+        name,
+    );
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut generics = input.generics.clone();
+    let clone_where = generics.make_where_clause();
+    for ty in &field_types
+    {
+        clone_where.predicates.push(syn::parse_quote!(#ty: ::core::clone::Clone));
+    }
+
+    Ok(quote!
+    {
+        #[doc = #twin_doc]
+        #vis struct #twin #impl_generics #where_clause
+        {
+            #(
+                #field_vis #field_names: ::dubble::DoubleBuffered<#field_types>,
+            )*
+        }
+
+        impl #impl_generics #twin #ty_generics #clone_where
+        {
+            /// Initialises both buffers of every field from `value`.
+            pub fn new(value: #name #ty_generics) -> Self
+            {
+                Self
+                {
+                    #(
+                        #field_names: ::dubble::DoubleBuffered::new(value.#field_names),
+                    )*
+                }
+            }
+
+            #(
+                #[doc = #read_docs]
+                pub fn #readers(&self) -> &#field_types
+                {
+                    self.#field_names.read()
+                }
+
+                #[doc = #write_docs]
+                pub fn #writers(&mut self) -> &mut #field_types
+                {
+                    self.#field_names.write()
+                }
+            )*
+
+            /// Copies the write buffer of every field into its read buffer.
+            pub fn update(&mut self)
+            {
+                #(
+                    self.#field_names.update();
+                )*
+            }
+
+            /// Returns a clone of the read buffers, as the original struct.
+            pub fn read(&self) -> #name #ty_generics
+            {
+                #name
+                {
+                    #(
+                        #field_names: ::core::clone::Clone::clone(self.#field_names.read()),
+                    )*
+                }
+            }
+
+            /// Returns the read buffers, as the original struct.
+            pub fn unbuffer_read(self) -> #name #ty_generics
+            {
+                #name
+                {
+                    #(
+                        #field_names: self.#field_names.unbuffer_read(),
+                    )*
+                }
+            }
+
+            /// Returns the write buffers, as the original struct.
+            pub fn unbuffer_write(self) -> #name #ty_generics
+            {
+                #name
+                {
+                    #(
+                        #field_names: self.#field_names.unbuffer_write(),
+                    )*
+                }
+            }
+        }
+
+        impl #impl_generics ::dubble::Updatable for #twin #ty_generics #clone_where
+        {
+            fn update(&mut self)
+            {
+                #twin::update(self);
+            }
+        }
+
+        impl #impl_generics ::core::convert::From<#name #ty_generics> for #twin #ty_generics #clone_where
+        {
+            fn from(value: #name #ty_generics) -> Self
+            {
+                Self::new(value)
+            }
+        }
+    })
+}
+
+/// The name of the generated struct; `FooBuffered` unless overridden with
+/// `#[double_buffer(name = "...")]`.
+fn twin_name(input: &DeriveInput) -> syn::Result<Ident>
+{
+    let mut name = format_ident!("{}Buffered", input.ident);
+
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("double_buffer"))
+    {
+        attr.parse_nested_meta(|meta|
+        {
+            if meta.path.is_ident("name")
+            {
+                let lit: LitStr = meta.value()?.parse()?;
+                name = Ident::new(&lit.value(), lit.span());
+                Ok(())
+            }
+            else
+            {
+                Err(meta.error("unknown double_buffer attribute"))
+            }
+        })?;
+    }
+
+    Ok(name)
+}
@@ -0,0 +1,80 @@
+use dubble::{BufferSet, DoubleBuffer, DoubleBuffered, Updatable};
+
+#[derive(DoubleBuffer, Clone, Debug, PartialEq)]
+pub struct Player
+{
+    pub hp: i32,
+    pub pos: (f32, f32),
+    inventory: Vec<String>,
+}
+
+#[derive(DoubleBuffer, Clone, Debug, PartialEq)]
+#[double_buffer(name = "Buffered")]
+struct Wrapper<T>
+where
+    T: Default,
+{
+    value: T,
+}
+
+fn player() -> Player
+{
+    Player
+    {
+        hp: 100,
+        pos: (0.0, 0.0),
+        inventory: vec!["sword".into()],
+    }
+}
+
+#[test]
+fn fields_are_double_buffered()
+{
+    let mut p = PlayerBuffered::new(player());
+
+    *p.write_hp() = 50;
+    p.pos.0 = 1.0;
+    p.write_inventory().push("shield".into());
+
+    assert!(*p.read_hp() == 100);
+    assert!(*p.pos == (0.0, 0.0));
+    assert!(p.read() == player());
+
+    p.update();
+
+    assert!(*p.read_hp() == 50);
+    assert!(*p.pos == (1.0, 0.0));
+    assert!(p.read_inventory().len() == 2);
+}
+
+#[test]
+fn unbuffer()
+{
+    let mut p = PlayerBuffered::from(player());
+    *p.write_hp() = 1;
+
+    let read = PlayerBuffered::from(player()).unbuffer_read();
+    assert!(read == player());
+
+    let written = p.unbuffer_write();
+    assert!(written.hp == 1);
+}
+
+#[test]
+fn generics_and_updatable()
+{
+    let mut w = Buffered::new(Wrapper { value: vec![1] });
+    let mut other = DoubleBuffered::new(0);
+
+    w.write_value().push(2);
+    *other = 3;
+
+    BufferSet::new().with(&mut w).with(&mut other).update();
+
+    assert!(*w.read_value() == [1, 2]);
+    assert!(*other == 3);
+
+    w.write_value().clear();
+    Updatable::update(&mut w);
+    assert!(w.read_value().is_empty());
+}
@@ -0,0 +1,79 @@
+//! Compares `update` with `par_update` for buffers of various sizes.
+//!
+//! `update` is timed with both the default `CloneCommit` strategy, which
+//! allocates a new buffer on every commit, and with `CloneFromCommit`, which
+//! reuses the old one like `par_update` does, so that the difference made by
+//! the threads themselves can be seen.
+//!
+//! Run with `cargo run --release --example parallel_commit`, and optionally
+//! `--features rayon`.
+
+extern crate dubble;
+
+use std::thread;
+use std::time::
+{
+    Duration,
+    Instant
+};
+
+use dubble::DoubleBuffered;
+use dubble::strategy::CloneFromCommit;
+
+const ROUNDS: u32 = 20;
+
+fn time<F: FnMut()>(mut f: F) -> Duration
+{
+    // warm up the caches and the allocator first
+    f();
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS
+    {
+        f();
+    }
+
+    start.elapsed() / ROUNDS
+}
+
+fn main()
+{
+    let cores = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    println!("{} cores", cores);
+    println!("{:>10} {:>12} {:>12} {:>12} {:>8}", "size", "clone", "clone_from", "par_update", "speedup");
+
+    for &kib in &[64, 256, 1024, 4 * 1024, 16 * 1024, 64 * 1024]
+    {
+        let len = kib * 1024 / 4;
+
+        let mut db = DoubleBuffered::new(vec![1.0f32; len]);
+        let clone = time(||
+        {
+            db[0] += 1.0;
+            db.update();
+        });
+
+        let mut db = DoubleBuffered::new(vec![1.0f32; len]).with_strategy::<CloneFromCommit>();
+        let clone_from = time(||
+        {
+            db[0] += 1.0;
+            db.update();
+        });
+
+        let mut db = DoubleBuffered::new(vec![1.0f32; len]);
+        let parallel = time(||
+        {
+            db[0] += 1.0;
+            db.par_update();
+        });
+
+        println!(
+            "{:>7}KiB {:>12?} {:>12?} {:>12?} {:>7.2}x",
+            kib,
+            clone,
+            clone_from,
+            parallel,
+            clone_from.as_secs_f64() / parallel.as_secs_f64(),
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Buffers which add up many contributions each frame.
+
+use std::mem;
+use std::ops::Deref;
+
+/// A way of combining values, with a value that combining with does nothing.
+///
+/// `combine` should be associative, and combining with `identity` should
+/// leave a value unchanged. Numbers combine by adding them up, and tuples and
+/// arrays combine element by element.
+pub trait Monoid
+{
+    /// The value which changes nothing when combined with, such as `0` for
+    /// addition.
+    fn identity() -> Self;
+
+    /// Combines `other` into `self`.
+    fn combine(&mut self, other: Self);
+}
+
+macro_rules! impl_monoid_by_addition
+{
+    ($($t:ty)*) =>
+    {
+        $(
+            impl Monoid for $t
+            {
+                fn identity() -> Self
+                {
+                    0 as $t
+                }
+
+                fn combine(&mut self, other: Self)
+                {
+                    *self += other;
+                }
+            }
+        )*
+    };
+}
+
+impl_monoid_by_addition!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize f32 f64);
+
+impl<T: Monoid, const N: usize> Monoid for [T; N]
+{
+    fn identity() -> Self
+    {
+        ::std::array::from_fn(|_| T::identity())
+    }
+
+    fn combine(&mut self, other: Self)
+    {
+        for (a, b) in self.iter_mut().zip(other)
+        {
+            a.combine(b);
+        }
+    }
+}
+
+macro_rules! impl_monoid_tuple
+{
+    ($($name:ident $idx:tt)+) =>
+    {
+        impl<$($name: Monoid),+> Monoid for ($($name,)+)
+        {
+            fn identity() -> Self
+            {
+                ($($name::identity(),)+)
+            }
+
+            fn combine(&mut self, other: Self)
+            {
+                $(self.$idx.combine(other.$idx);)+
+            }
+        }
+    };
+}
+
+impl_monoid_tuple!(A 0);
+impl_monoid_tuple!(A 0 B 1);
+impl_monoid_tuple!(A 0 B 1 C 2);
+impl_monoid_tuple!(A 0 B 1 C 2 D 3);
+impl_monoid_tuple!(A 0 B 1 C 2 D 3 E 4);
+impl_monoid_tuple!(A 0 B 1 C 2 D 3 E 4 F 5);
+impl_monoid_tuple!(A 0 B 1 C 2 D 3 E 4 F 5 G 6);
+impl_monoid_tuple!(A 0 B 1 C 2 D 3 E 4 F 5 G 6 H 7);
+impl_monoid_tuple!(A 0 B 1 C 2 D 3 E 4 F 5 G 6 H 7 I 8);
+impl_monoid_tuple!(A 0 B 1 C 2 D 3 E 4 F 5 G 6 H 7 I 8 J 9);
+impl_monoid_tuple!(A 0 B 1 C 2 D 3 E 4 F 5 G 6 H 7 I 8 J 9 K 10);
+impl_monoid_tuple!(A 0 B 1 C 2 D 3 E 4 F 5 G 6 H 7 I 8 J 9 K 10 L 11);
+
+/// A double-buffer whose write side collects contributions, which `update`
+/// publishes as a single combined value.
+///
+/// Unlike `DoubleBuffered`, the write side doesn't carry over between frames:
+/// after `update`, it starts again from `Monoid::identity`. Since the total is
+/// moved across rather than copied, `T` doesn't need to be `Clone`.
+///
+/// ```rust
+/// use dubble::AccumulatingBuffered;
+///
+/// // total force on a body, and the number of things pushing it
+/// let mut force = AccumulatingBuffered::<([f32; 2], u32)>::new();
+///
+/// force.add(([1.0, 0.0], 1));
+/// force.add(([0.0, -9.8], 1));
+/// assert!(force.read().1 == 0);
+///
+/// force.update();
+/// assert!(*force == ([1.0, -9.8], 2));
+///
+/// // the next frame starts from nothing
+/// force.add(([2.0, 2.0], 1));
+/// force.update();
+/// assert!(*force == ([2.0, 2.0], 1));
+/// ```
+pub struct AccumulatingBuffered<T: Monoid>
+{
+    rbuf: T,
+    wbuf: T,
+}
+
+impl<T: Monoid> AccumulatingBuffered<T>
+{
+    /// Creates a buffer with both sides set to the identity.
+    pub fn new() -> Self
+    {
+        Self
+        {
+            rbuf: T::identity(),
+            wbuf: T::identity(),
+        }
+    }
+
+    /// Returns the total published by the last `update`.
+    pub fn read(&self) -> &T
+    {
+        &self.rbuf
+    }
+
+    /// Returns what has been added up since the last `update`.
+    pub fn pending(&self) -> &T
+    {
+        &self.wbuf
+    }
+
+    /// Combines a contribution into the write side.
+    pub fn add(&mut self, value: T)
+    {
+        self.wbuf.combine(value);
+    }
+
+    /// Publishes the combined contributions, and resets the write side to the
+    /// identity.
+    pub fn update(&mut self)
+    {
+        self.rbuf = mem::replace(&mut self.wbuf, T::identity());
+    }
+
+    /// Returns the read side, discarding the write side.
+    pub fn unbuffer_read(self) -> T
+    {
+        self.rbuf
+    }
+
+    /// Returns the write side, discarding the read side.
+    pub fn unbuffer_write(self) -> T
+    {
+        self.wbuf
+    }
+}
+
+impl<T: Monoid> Default for AccumulatingBuffered<T>
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl<T: Monoid> Deref for AccumulatingBuffered<T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &T
+    {
+        self.read()
+    }
+}
+
+impl<T: Monoid> Extend<T> for AccumulatingBuffered<T>
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I)
+    {
+        for value in iter
+        {
+            self.add(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// The largest value seen, as a user-provided monoid.
+    #[derive(Debug, PartialEq)]
+    struct Max(u32);
+
+    impl Monoid for Max
+    {
+        fn identity() -> Self
+        {
+            Max(0)
+        }
+
+        fn combine(&mut self, other: Self)
+        {
+            self.0 = self.0.max(other.0);
+        }
+    }
+
+    #[test]
+    fn resets_every_frame()
+    {
+        let mut damage = AccumulatingBuffered::<u32>::new();
+        damage.extend(vec![5u32, 10, 20]);
+        assert!(*damage.pending() == 35);
+        assert!(*damage == 0);
+
+        damage.update();
+        assert!(*damage == 35);
+        assert!(*damage.pending() == 0);
+
+        // nothing this frame
+        damage.update();
+        assert!(*damage == 0);
+    }
+
+    #[test]
+    fn user_monoid()
+    {
+        let mut peak = AccumulatingBuffered::<Max>::new();
+        peak.extend(vec![Max(3), Max(7), Max(5)]);
+        peak.update();
+        assert!(*peak == Max(7));
+
+        let mut stats = AccumulatingBuffered::<(Max, u32)>::new();
+        stats.add((Max(2), 1));
+        stats.add((Max(1), 1));
+        assert!(stats.unbuffer_write() == (Max(2), 2));
+    }
+}
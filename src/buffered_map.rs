@@ -0,0 +1,308 @@
+//! A double-buffered `HashMap` which only copies the entries that changed.
+
+use std::borrow::Borrow;
+use std::collections::
+{
+    HashMap,
+    HashSet
+};
+use std::collections::hash_map;
+use std::hash::Hash;
+use std::iter::FromIterator;
+use std::ops::Index;
+
+/// A double-buffered `HashMap` which records which keys have been written
+/// to, so that `update` only has to copy those entries.
+///
+/// Reads (`get`, `iter`, indexing) see the read buffer. Writes (`get_mut`,
+/// `insert`, `remove`) go to the write buffer and only become visible after
+/// `update`, which costs time proportional to the number of keys written,
+/// rather than to the size of the map.
+///
+/// ```rust
+/// use dubble::DoubleBufferedMap;
+///
+/// let mut scores = DoubleBufferedMap::new();
+/// scores.insert("alice", 10);
+/// scores.insert("bob", 20);
+/// scores.update();
+///
+/// *scores.get_mut("alice").unwrap() += 5;
+/// scores.remove("bob");
+/// assert!(scores["alice"] == 10);
+/// assert!(scores.contains_key("bob"));
+///
+/// // only the entries for "alice" and "bob" are touched
+/// scores.update();
+/// assert!(scores["alice"] == 15);
+/// assert!(!scores.contains_key("bob"));
+/// ```
+pub struct DoubleBufferedMap<K: Eq + Hash + Clone, V: Clone>
+{
+    rbuf: HashMap<K, V>,
+    wbuf: HashMap<K, V>,
+    changed: HashSet<K>,
+    all_changed: bool,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> DoubleBufferedMap<K, V>
+{
+    /// Creates an empty map.
+    pub fn new() -> Self
+    {
+        Self::from(HashMap::new())
+    }
+
+    /// Returns an immutable reference to the read buffer.
+    pub fn read(&self) -> &HashMap<K, V>
+    {
+        &self.rbuf
+    }
+
+    /// Returns a mutable reference to the whole write buffer. Since there's no
+    /// way of knowing what gets changed through this, the whole map is copied
+    /// during the next `update`.
+    pub fn write(&mut self) -> &mut HashMap<K, V>
+    {
+        self.all_changed = true;
+        &mut self.wbuf
+    }
+
+    /// Returns a reference to the value for `key` in the read buffer.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+        where K: Borrow<Q>,
+              Q: Eq + Hash + ?Sized
+    {
+        self.rbuf.get(key)
+    }
+
+    /// Returns whether the read buffer has an entry for `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+        where K: Borrow<Q>,
+              Q: Eq + Hash + ?Sized
+    {
+        self.rbuf.contains_key(key)
+    }
+
+    /// Returns a mutable reference to the value for `key` in the write buffer,
+    /// and marks the key as changed.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+        where K: Borrow<Q>,
+              Q: Eq + Hash + ?Sized
+    {
+        if !self.changed.contains(key)
+        {
+            // we were only given something the key can be borrowed as, so get
+            // an owned copy of it from the map
+            let owned = self.wbuf.get_key_value(key)?.0.clone();
+            self.changed.insert(owned);
+        }
+
+        self.wbuf.get_mut(key)
+    }
+
+    /// Inserts an entry into the write buffer, returning the value it replaced
+    /// there, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V>
+    {
+        self.changed.insert(key.clone());
+        self.wbuf.insert(key, value)
+    }
+
+    /// Removes an entry from the write buffer, returning its value, if any.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+        where K: Borrow<Q>,
+              Q: Eq + Hash + ?Sized
+    {
+        let (key, value) = self.wbuf.remove_entry(key)?;
+        self.changed.insert(key);
+        Some(value)
+    }
+
+    /// Returns the number of entries in the read buffer.
+    pub fn len(&self) -> usize
+    {
+        self.rbuf.len()
+    }
+
+    /// Returns whether the read buffer is empty.
+    pub fn is_empty(&self) -> bool
+    {
+        self.rbuf.is_empty()
+    }
+
+    /// Iterates over the entries in the read buffer.
+    pub fn iter(&self) -> hash_map::Iter<'_, K, V>
+    {
+        self.rbuf.iter()
+    }
+
+    /// Iterates over the keys which have been written to since the last
+    /// `update`, in no particular order. If the whole map has been handed out
+    /// with `write`, that's every key in either buffer.
+    pub fn changed_keys(&self) -> Box<dyn Iterator<Item = &K> + '_>
+    {
+        if self.all_changed
+        {
+            let removed = self.rbuf.keys().filter(move |k| !self.wbuf.contains_key(*k));
+            Box::new(self.wbuf.keys().chain(removed))
+        }
+        else
+        {
+            Box::new(self.changed.iter())
+        }
+    }
+
+    /// Copies the changed entries of the write buffer into the read buffer,
+    /// and removes the entries which were removed from it.
+    pub fn update(&mut self)
+    {
+        if self.all_changed
+        {
+            self.rbuf.clone_from(&self.wbuf);
+        }
+        else
+        {
+            for key in self.changed.drain()
+            {
+                match self.wbuf.get(&key)
+                {
+                    Some(value) => match self.rbuf.get_mut(&key)
+                    {
+                        Some(old) => old.clone_from(value),
+                        None      => { self.rbuf.insert(key, value.clone()); }
+                    },
+
+                    None => { self.rbuf.remove(&key); }
+                }
+            }
+        }
+
+        self.changed.clear();
+        self.all_changed = false;
+    }
+
+    /// Returns the read buffer, discarding the write buffer.
+    pub fn unbuffer_read(self) -> HashMap<K, V>
+    {
+        self.rbuf
+    }
+
+    /// Returns the write buffer, discarding the read buffer.
+    pub fn unbuffer_write(self) -> HashMap<K, V>
+    {
+        self.wbuf
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Default for DoubleBufferedMap<K, V>
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> From<HashMap<K, V>> for DoubleBufferedMap<K, V>
+{
+    fn from(value: HashMap<K, V>) -> Self
+    {
+        Self
+        {
+            rbuf: value.clone(),
+            wbuf: value,
+            changed: HashSet::new(),
+            all_changed: false,
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> FromIterator<(K, V)> for DoubleBufferedMap<K, V>
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self
+    {
+        Self::from(iter.into_iter().collect::<HashMap<_, _>>())
+    }
+}
+
+impl<K, Q, V> Index<&Q> for DoubleBufferedMap<K, V>
+    where K: Eq + Hash + Clone + Borrow<Q>,
+          Q: Eq + Hash + ?Sized,
+          V: Clone
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V
+    {
+        &self.rbuf[key]
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn only_changed_keys_are_copied()
+    {
+        let mut db: DoubleBufferedMap<_, _> = (0..10).map(|i| (i, i)).collect();
+
+        // sneak a change past the tracking, it should not be committed
+        *db.wbuf.get_mut(&0).unwrap() = 100;
+
+        *db.get_mut(&1).unwrap() = 10;
+        db.insert(20, 20);
+        db.remove(&2);
+
+        let mut changed: Vec<_> = db.changed_keys().cloned().collect();
+        changed.sort();
+        assert!(changed == [1, 2, 20]);
+        assert!(db[&1] == 1 && db.contains_key(&2) && db.get(&20).is_none());
+
+        db.update();
+        assert!(db[&0] == 0);
+        assert!(db[&1] == 10);
+        assert!(!db.contains_key(&2));
+        assert!(db[&20] == 20);
+        assert!(db.len() == 10);
+        assert!(db.changed_keys().next().is_none());
+    }
+
+    #[test]
+    fn insert_then_write_then_remove()
+    {
+        let mut db = DoubleBufferedMap::new();
+        db.insert(String::from("a"), 1);
+        *db.get_mut("a").unwrap() += 1;
+        db.update();
+        assert!(db["a"] == 2);
+
+        *db.get_mut("a").unwrap() += 1;
+        db.remove("a");
+        assert!(db.get_mut("missing").is_none());
+        db.update();
+        assert!(db.is_empty());
+    }
+
+    #[test]
+    fn whole_map_writes()
+    {
+        let mut db = DoubleBufferedMap::new();
+        db.insert(1, 'a');
+        db.insert(2, 'b');
+        db.update();
+
+        db.write().retain(|&k, _| k != 1);
+        db.write().insert(3, 'c');
+
+        let mut changed: Vec<_> = db.changed_keys().cloned().collect();
+        changed.sort();
+        assert!(changed == [1, 2, 3]);
+
+        db.update();
+        let mut entries: Vec<_> = db.iter().map(|(&k, &v)| (k, v)).collect();
+        entries.sort();
+        assert!(entries == [(2, 'b'), (3, 'c')]);
+    }
+}
@@ -0,0 +1,342 @@
+//! A double-buffered collection whose structure only changes on `update`.
+
+use std::ops::
+{
+    Index,
+    IndexMut
+};
+
+/// Refers to an element of a `DoubleBufferedVec`.
+///
+/// A handle stays valid until its element is removed; after that it will
+/// never refer to anything again, even if the slot is reused for a new
+/// element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VecHandle
+{
+    index: usize,
+    generation: u32,
+}
+
+impl VecHandle
+{
+    /// Returns the slot this handle refers to. Slots are reused once their
+    /// element has been removed, so don't use this as an identifier.
+    pub fn index(&self) -> usize
+    {
+        self.index
+    }
+}
+
+/// A double-buffered collection of elements, where changes to elements are
+/// double-buffered as usual, and changes to the structure of the collection
+/// are deferred until `update`.
+///
+/// With a `DoubleBuffered<Vec<T>>`, pushing or removing elements through the
+/// write buffer makes indices disagree between the two buffers until the
+/// next commit. Here, `insert` and `remove` are queued instead, and applied
+/// at `update`, all the insertions first and then all the removals, so
+/// during a frame every handle refers to the same element in both buffers.
+/// An element inserted and removed in the same frame is never committed.
+///
+/// ```rust
+/// use dubble::DoubleBufferedVec;
+///
+/// let mut actors = DoubleBufferedVec::new();
+/// let player = actors.insert(100);
+/// let monster = actors.insert(50);
+/// actors.update();
+///
+/// // reads and writes by handle agree on which element is which
+/// actors[monster] -= actors[player] / 10;
+///
+/// // structural changes wait for the update
+/// actors.remove(player);
+/// assert!(actors[player] == 100);
+///
+/// actors.update();
+/// assert!(actors.get(player).is_none());
+/// assert!(actors[monster] == 40);
+/// ```
+pub struct DoubleBufferedVec<T: Clone>
+{
+    rbuf: Vec<Option<T>>,
+    wbuf: Vec<Option<T>>,
+
+    /// Generation of each slot, bumped whenever its element is removed.
+    generations: Vec<u32>,
+
+    /// Slots which are empty in both buffers, and haven't been handed out.
+    free: Vec<usize>,
+    inserts: Vec<(usize, T)>,
+    removals: Vec<VecHandle>,
+}
+
+impl<T: Clone> DoubleBufferedVec<T>
+{
+    /// Creates an empty collection.
+    pub fn new() -> Self
+    {
+        Self
+        {
+            rbuf: Vec::new(),
+            wbuf: Vec::new(),
+            generations: Vec::new(),
+            free: Vec::new(),
+            inserts: Vec::new(),
+            removals: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to an element in the read buffer, or `None` if the
+    /// handle doesn't refer to a committed element.
+    pub fn get(&self, handle: VecHandle) -> Option<&T>
+    {
+        if !self.is_current(handle)
+        {
+            return None;
+        }
+
+        self.rbuf.get(handle.index).and_then(Option::as_ref)
+    }
+
+    /// Returns a mutable reference to an element in the write buffer, or
+    /// `None` if the handle doesn't refer to a committed element.
+    pub fn get_mut(&mut self, handle: VecHandle) -> Option<&mut T>
+    {
+        if !self.is_current(handle)
+        {
+            return None;
+        }
+
+        self.wbuf.get_mut(handle.index).and_then(Option::as_mut)
+    }
+
+    /// Returns whether the handle refers to a committed element.
+    pub fn contains(&self, handle: VecHandle) -> bool
+    {
+        self.get(handle).is_some()
+    }
+
+    /// Queues an element to be added at the next `update`, and returns the
+    /// handle it will have.
+    pub fn insert(&mut self, value: T) -> VecHandle
+    {
+        let index = match self.free.pop()
+        {
+            Some(index) => index,
+            None =>
+            {
+                self.generations.push(0);
+                self.generations.len() - 1
+            }
+        };
+
+        self.inserts.push((index, value));
+        VecHandle { index, generation: self.generations[index] }
+    }
+
+    /// Queues an element to be removed at the next `update`. Removing an
+    /// element which doesn't exist (or won't, once queued insertions have been
+    /// applied) does nothing.
+    pub fn remove(&mut self, handle: VecHandle)
+    {
+        self.removals.push(handle);
+    }
+
+    /// Returns the number of committed elements.
+    pub fn len(&self) -> usize
+    {
+        self.rbuf.iter().filter(|x| x.is_some()).count()
+    }
+
+    /// Returns whether there are no committed elements.
+    pub fn is_empty(&self) -> bool
+    {
+        self.rbuf.iter().all(Option::is_none)
+    }
+
+    /// Iterates over the committed elements in the read buffer, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (VecHandle, &T)>
+    {
+        let generations = &self.generations;
+        self.rbuf.iter()
+            .enumerate()
+            .filter_map(move |(index, x)| x.as_ref().map(|x|
+            {
+                (VecHandle { index, generation: generations[index] }, x)
+            }))
+    }
+
+    /// Iterates over the committed elements in the write buffer, in slot
+    /// order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (VecHandle, &mut T)>
+    {
+        let generations = &self.generations;
+        self.wbuf.iter_mut()
+            .enumerate()
+            .filter_map(move |(index, x)| x.as_mut().map(|x|
+            {
+                (VecHandle { index, generation: generations[index] }, x)
+            }))
+    }
+
+    /// Applies queued insertions, then queued removals, and then copies the
+    /// write buffer into the read buffer. Handles to removed elements stop
+    /// being valid.
+    pub fn update(&mut self)
+    {
+        for (index, value) in self.inserts.drain(..)
+        {
+            if self.wbuf.len() <= index
+            {
+                self.wbuf.resize(index + 1, None);
+            }
+
+            self.wbuf[index] = Some(value);
+        }
+
+        for handle in self.removals.drain(..)
+        {
+            if self.generations.get(handle.index) != Some(&handle.generation)
+            {
+                continue;
+            }
+
+            if let Some(slot @ &mut Some(_)) = self.wbuf.get_mut(handle.index)
+            {
+                *slot = None;
+                self.generations[handle.index] = handle.generation.wrapping_add(1);
+                self.free.push(handle.index);
+            }
+        }
+
+        self.rbuf.clone_from(&self.wbuf);
+    }
+
+    fn is_current(&self, handle: VecHandle) -> bool
+    {
+        self.generations.get(handle.index) == Some(&handle.generation)
+    }
+}
+
+impl<T: Clone> Default for DoubleBufferedVec<T>
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl<T: Clone> From<Vec<T>> for DoubleBufferedVec<T>
+{
+    /// Creates a collection with the elements already committed, in slots
+    /// `0..value.len()`.
+    fn from(value: Vec<T>) -> Self
+    {
+        let wbuf: Vec<_> = value.into_iter().map(Some).collect();
+
+        Self
+        {
+            rbuf: wbuf.clone(),
+            generations: vec![0; wbuf.len()],
+            wbuf,
+            ..Self::new()
+        }
+    }
+}
+
+impl<T: Clone> Index<VecHandle> for DoubleBufferedVec<T>
+{
+    type Output = T;
+
+    fn index(&self, handle: VecHandle) -> &T
+    {
+        self.get(handle).expect("no committed element for this handle")
+    }
+}
+
+impl<T: Clone> IndexMut<VecHandle> for DoubleBufferedVec<T>
+{
+    fn index_mut(&mut self, handle: VecHandle) -> &mut T
+    {
+        self.get_mut(handle).expect("no committed element for this handle")
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn structure_is_frozen_until_update()
+    {
+        let mut v = DoubleBufferedVec::from(vec![1, 2, 3]);
+        let handles: Vec<_> = v.iter().map(|(h, _)| h).collect();
+
+        let four = v.insert(4);
+        v.remove(handles[0]);
+        v[handles[1]] = 20;
+
+        // nothing structural has happened yet
+        assert!(v.get(four).is_none());
+        assert!(v.iter().map(|(_, &x)| x).collect::<Vec<_>>() == [1, 2, 3]);
+
+        v.update();
+        assert!(v.iter().map(|(_, &x)| x).collect::<Vec<_>>() == [20, 3, 4]);
+        assert!(v.len() == 3);
+        assert!(!v.contains(handles[0]));
+        assert!(v[four] == 4);
+    }
+
+    #[test]
+    fn stale_handles_stay_stale()
+    {
+        let mut v = DoubleBufferedVec::new();
+        let a = v.insert("a");
+        v.update();
+
+        v.remove(a);
+        v.update();
+
+        // the slot is reused, but the old handle doesn't see the new element
+        let b = v.insert("b");
+        v.update();
+        assert!(a.index() == b.index());
+        assert!(v.get(a).is_none());
+        assert!(v.get_mut(a).is_none());
+        assert!(v[b] == "b");
+
+        // removing through a stale handle does nothing
+        v.remove(a);
+        v.update();
+        assert!(v[b] == "b");
+    }
+
+    #[test]
+    fn insert_and_remove_in_one_frame()
+    {
+        let mut v = DoubleBufferedVec::new();
+        let a = v.insert(1);
+        v.remove(a);
+        v.update();
+
+        assert!(v.is_empty());
+        assert!(!v.contains(a));
+    }
+
+    #[test]
+    fn iter_mut_writes_are_buffered()
+    {
+        let mut v = DoubleBufferedVec::from(vec![1, 2]);
+        for (_, x) in v.iter_mut()
+        {
+            *x *= 10;
+        }
+
+        assert!(v.iter().map(|(_, &x)| x).collect::<Vec<_>>() == [1, 2]);
+        v.update();
+        assert!(v.iter().map(|(_, &x)| x).collect::<Vec<_>>() == [10, 20]);
+    }
+}
@@ -0,0 +1,372 @@
+//! Typed differences between values.
+//!
+//! `Diff` describes how to get from one value to another, and how to replay
+//! that change onto a value. `DoubleBuffered::pending_diff` uses it to
+//! describe what has been written since the last commit, which can be logged,
+//! or sent to another machine and replayed there with
+//! `DoubleBuffered::apply_diff`.
+//!
+//! ```rust
+//! use dubble::DoubleBuffered;
+//!
+//! let mut server = DoubleBuffered::new(vec![1, 2, 3]);
+//! let mut client = DoubleBuffered::new(vec![1, 2, 3]);
+//!
+//! server[1] = 20;
+//! server.push(4);
+//!
+//! let delta = server.pending_diff().unwrap();
+//! assert!(delta.changed == [(1, 20)]);
+//! assert!(delta.appended == [4]);
+//! server.update();
+//!
+//! client.apply_diff(&delta);
+//! client.update();
+//! assert!(*client == *server);
+//! ```
+//!
+//! With the `serde` feature enabled, the delta types can be serialized.
+
+use std::collections::HashMap;
+use std::hash::
+{
+    BuildHasher,
+    Hash
+};
+
+use DoubleBuffered;
+
+/// A type whose changes can be described by a `Delta`.
+pub trait Diff
+{
+    /// Describes a change to a value of this type.
+    type Delta;
+
+    /// Returns the change which turns `self` into `new`, or `None` if they
+    /// are the same.
+    fn diff(&self, new: &Self) -> Option<Self::Delta>;
+
+    /// Replays a change returned by `diff`.
+    fn apply(&mut self, delta: &Self::Delta);
+}
+
+macro_rules! impl_diff_by_value
+{
+    ($($t:ty)*) =>
+    {
+        $(
+            impl Diff for $t
+            {
+                /// The new value.
+                type Delta = $t;
+
+                fn diff(&self, new: &Self) -> Option<$t>
+                {
+                    if self != new { Some(*new) } else { None }
+                }
+
+                fn apply(&mut self, delta: &$t)
+                {
+                    *self = *delta;
+                }
+            }
+        )*
+    };
+}
+
+impl_diff_by_value!(bool char u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize f32 f64);
+
+/// The change between two `String`s: the common prefix is kept, and the rest
+/// is replaced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct StringDelta
+{
+    /// Number of bytes at the start of the string which are unchanged.
+    pub keep: usize,
+
+    /// What comes after them.
+    pub tail: String,
+}
+
+impl Diff for String
+{
+    type Delta = StringDelta;
+
+    fn diff(&self, new: &Self) -> Option<StringDelta>
+    {
+        if self == new
+        {
+            return None;
+        }
+
+        let keep = self.char_indices()
+            .zip(new.chars())
+            .find(|&((_, a), b)| a != b)
+            .map(|((i, _), _)| i)
+            .unwrap_or_else(|| self.len().min(new.len()));
+
+        Some(StringDelta { keep, tail: new[keep..].to_string() })
+    }
+
+    fn apply(&mut self, delta: &StringDelta)
+    {
+        self.truncate(delta.keep);
+        self.push_str(&delta.tail);
+    }
+}
+
+/// The change between two `Vec`s.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(
+    serialize = "T: ::serde::Serialize, T::Delta: ::serde::Serialize",
+    deserialize = "T: ::serde::Deserialize<'de>, T::Delta: ::serde::Deserialize<'de>",
+)))]
+pub struct VecDelta<T: Diff>
+{
+    /// Elements which exist in both, and changed.
+    pub changed: Vec<(usize, T::Delta)>,
+
+    /// The new length, before `appended` is added.
+    pub len: usize,
+
+    /// Elements added to the end.
+    pub appended: Vec<T>,
+}
+
+impl<T: Diff + Clone> Diff for Vec<T>
+{
+    type Delta = VecDelta<T>;
+
+    fn diff(&self, new: &Self) -> Option<VecDelta<T>>
+    {
+        let changed: Vec<_> = self.iter()
+            .zip(new)
+            .enumerate()
+            .filter_map(|(i, (a, b))| a.diff(b).map(|d| (i, d)))
+            .collect();
+
+        let len = self.len().min(new.len());
+        let appended = new[len..].to_vec();
+
+        if changed.is_empty() && appended.is_empty() && len == self.len()
+        {
+            return None;
+        }
+
+        Some(VecDelta { changed, len, appended })
+    }
+
+    fn apply(&mut self, delta: &VecDelta<T>)
+    {
+        for &(i, ref d) in &delta.changed
+        {
+            self[i].apply(d);
+        }
+
+        self.truncate(delta.len);
+        self.extend_from_slice(&delta.appended);
+    }
+}
+
+/// The change between two `HashMap`s.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(
+    serialize = "K: ::serde::Serialize, V: ::serde::Serialize, V::Delta: ::serde::Serialize",
+    deserialize = "K: ::serde::Deserialize<'de>, V: ::serde::Deserialize<'de>, V::Delta: ::serde::Deserialize<'de>",
+)))]
+pub struct MapDelta<K, V: Diff>
+{
+    /// Entries which exist in both, and changed.
+    pub changed: Vec<(K, V::Delta)>,
+
+    /// Entries which are new.
+    pub inserted: Vec<(K, V)>,
+
+    /// Keys which were removed.
+    pub removed: Vec<K>,
+}
+
+impl<K, V, S> Diff for HashMap<K, V, S>
+    where K: Eq + Hash + Clone,
+          V: Diff + Clone,
+          S: BuildHasher
+{
+    type Delta = MapDelta<K, V>;
+
+    fn diff(&self, new: &Self) -> Option<MapDelta<K, V>>
+    {
+        let mut delta = MapDelta
+        {
+            changed: Vec::new(),
+            inserted: Vec::new(),
+            removed: Vec::new(),
+        };
+
+        for (k, v) in new
+        {
+            match self.get(k)
+            {
+                Some(old) =>
+                {
+                    if let Some(d) = old.diff(v)
+                    {
+                        delta.changed.push((k.clone(), d));
+                    }
+                }
+
+                None => delta.inserted.push((k.clone(), v.clone())),
+            }
+        }
+
+        delta.removed.extend(self.keys().filter(|k| !new.contains_key(k)).cloned());
+
+        if delta.changed.is_empty() && delta.inserted.is_empty() && delta.removed.is_empty()
+        {
+            return None;
+        }
+
+        Some(delta)
+    }
+
+    fn apply(&mut self, delta: &MapDelta<K, V>)
+    {
+        for k in &delta.removed
+        {
+            self.remove(k);
+        }
+
+        for (k, d) in &delta.changed
+        {
+            if let Some(v) = self.get_mut(k)
+            {
+                v.apply(d);
+            }
+        }
+
+        for (k, v) in &delta.inserted
+        {
+            self.insert(k.clone(), v.clone());
+        }
+    }
+}
+
+impl<T: Diff, S> DoubleBuffered<T, S>
+{
+    /// Returns how the write buffer differs from the read buffer; that is,
+    /// what the next `update` will change. `None` means nothing will.
+    pub fn pending_diff(&self) -> Option<T::Delta>
+    {
+        self.rbuf.diff(&self.wbuf)
+    }
+
+    /// Replays a change onto the write buffer. The change becomes visible
+    /// after the next `update`, like any other write.
+    pub fn apply_diff(&mut self, delta: &T::Delta)
+    {
+        self.write().apply(delta);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn round_trip<T: Diff + Clone + PartialEq>(old: T, new: T)
+    {
+        let mut replayed = old.clone();
+        if let Some(delta) = old.diff(&new)
+        {
+            replayed.apply(&delta);
+        }
+
+        assert!(replayed == new);
+    }
+
+    #[test]
+    fn primitives()
+    {
+        assert!(1.diff(&1).is_none());
+        assert!(1.diff(&2) == Some(2));
+        round_trip(true, false);
+        round_trip(0.5, 1.5);
+    }
+
+    #[test]
+    fn strings()
+    {
+        assert!(String::from("abc").diff(&"abc".into()).is_none());
+        assert!(String::from("hello").diff(&"help".into())
+            == Some(StringDelta { keep: 3, tail: "p".into() }));
+
+        round_trip(String::from("héllo"), "hérmit".into());
+        round_trip(String::from("abc"), "ab".into());
+        round_trip(String::from("ab"), "abc".into());
+        round_trip(String::new(), "new".into());
+    }
+
+    #[test]
+    fn vecs()
+    {
+        assert!(vec![1, 2].diff(&vec![1, 2]).is_none());
+
+        let delta = vec![1, 2, 3].diff(&vec![1, 5]).unwrap();
+        assert!(delta.changed == [(1, 5)]);
+        assert!(delta.len == 2);
+        assert!(delta.appended.is_empty());
+
+        round_trip(vec![1, 2, 3], vec![1, 5]);
+        round_trip(vec![1], vec![0, 2, 3]);
+        round_trip(vec![String::from("a")], vec!["b".into(), "c".into()]);
+    }
+
+    #[test]
+    fn maps()
+    {
+        let old: HashMap<_, _> = vec![(1, 10), (2, 20), (3, 30)].into_iter().collect();
+        let new: HashMap<_, _> = vec![(1, 10), (2, 21), (4, 40)].into_iter().collect();
+
+        assert!(old.diff(&old.clone()).is_none());
+
+        let delta = old.diff(&new).unwrap();
+        assert!(delta.changed == [(2, 21)]);
+        assert!(delta.inserted == [(4, 40)]);
+        assert!(delta.removed == [3]);
+
+        round_trip(old, new);
+    }
+
+    #[test]
+    fn pending_diff()
+    {
+        let mut db = DoubleBuffered::new(String::from("frame"));
+        assert!(db.pending_diff().is_none());
+
+        db.push_str(" two");
+        let delta = db.pending_diff().unwrap();
+
+        let mut other = DoubleBuffered::new(String::from("frame"));
+        other.apply_diff(&delta);
+        assert!(other.has_pending_changes());
+        other.update();
+        assert!(*other == "frame two");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_delta()
+    {
+        use serde_json;
+
+        let delta = vec![String::from("a"), "b".into()]
+            .diff(&vec!["a".into(), "c".into(), "d".into()])
+            .unwrap();
+
+        let json = serde_json::to_string(&delta).unwrap();
+        let back: VecDelta<String> = serde_json::from_str(&json).unwrap();
+        assert!(back == delta);
+    }
+}
@@ -0,0 +1,377 @@
+//! A double-buffered `Vec` which only copies what was written.
+
+use std::cmp;
+use std::ops::
+{
+    Bound,
+    Deref,
+    DerefMut,
+    Index,
+    IndexMut,
+    Range,
+    RangeBounds
+};
+use std::slice::SliceIndex;
+
+/// The fewest dirty ranges `DirtyVec` holds before coalescing them.
+const MIN_COALESCE_AT: usize = 64;
+
+/// A double-buffered `Vec` which records which elements of the write buffer
+/// have been touched, so that `update` only has to copy those.
+///
+/// Writes through `IndexMut`, `set`, `write_range` and `push` are tracked
+/// precisely. Anything else that needs the whole `Vec` (`write`, or calling a
+/// `Vec` method through `DerefMut`) marks the entire buffer as dirty, and the
+/// next `update` falls back to copying everything.
+///
+/// ```rust
+/// use dubble::DirtyVec;
+///
+/// let mut my_buf = DirtyVec::new(vec![0; 100_000]);
+///
+/// my_buf[10] = 1;
+/// my_buf.write_range(500..503).copy_from_slice(&[2, 3, 4]);
+/// assert!(my_buf[10] == 0);
+///
+/// // only the four touched elements are copied
+/// my_buf.update();
+/// assert!(my_buf[10] == 1);
+/// assert!(my_buf[500..503] == [2, 3, 4]);
+/// ```
+pub struct DirtyVec<T: Clone>
+{
+    rbuf: Vec<T>,
+    wbuf: Vec<T>,
+    dirty: Vec<Range<usize>>,
+    all_dirty: bool,
+
+    /// How many ranges `dirty` can hold before they're coalesced.
+    coalesce_at: usize,
+}
+
+impl<T: Clone> DirtyVec<T>
+{
+    /// Initialises both buffers with `value`.
+    pub fn new(value: Vec<T>) -> Self
+    {
+        Self
+        {
+            rbuf: value.clone(),
+            wbuf: value,
+            dirty: Vec::new(),
+            all_dirty: false,
+            coalesce_at: MIN_COALESCE_AT,
+        }
+    }
+
+    /// Returns an immutable reference to the read buffer.
+    pub fn read(&self) -> &Vec<T>
+    {
+        &self.rbuf
+    }
+
+    /// Returns a mutable reference to the whole write buffer. Since there's no
+    /// way of knowing what gets changed through this, the whole buffer is
+    /// copied during the next `update`.
+    pub fn write(&mut self) -> &mut Vec<T>
+    {
+        self.all_dirty = true;
+        &mut self.wbuf
+    }
+
+    /// Returns a mutable slice of the write buffer, and marks that range as
+    /// dirty.
+    ///
+    /// # Panics
+    ///
+    /// If the range is out of bounds of the write buffer.
+    pub fn write_range<R: RangeBounds<usize>>(&mut self, range: R) -> &mut [T]
+    {
+        let start = match range.start_bound()
+        {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s + 1,
+            Bound::Unbounded    => 0,
+        };
+
+        let end = match range.end_bound()
+        {
+            Bound::Included(&e) => e + 1,
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded    => self.wbuf.len(),
+        };
+
+        let slice = &mut self.wbuf[start..end];
+        Self::mark(&mut self.dirty, &mut self.coalesce_at, start..end);
+        slice
+    }
+
+    /// Sets the element at `index` in the write buffer.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds of the write buffer.
+    pub fn set(&mut self, index: usize, value: T)
+    {
+        self[index] = value;
+    }
+
+    /// Appends an element to the back of the write buffer.
+    pub fn push(&mut self, value: T)
+    {
+        let len = self.wbuf.len();
+        Self::mark(&mut self.dirty, &mut self.coalesce_at, len..len + 1);
+        self.wbuf.push(value);
+    }
+
+    /// Removes the last element from the write buffer and returns it.
+    pub fn pop(&mut self) -> Option<T>
+    {
+        // shrinking is picked up by `update` from the length alone
+        self.wbuf.pop()
+    }
+
+    /// Returns the ranges of the write buffer which have been written to since
+    /// the last `update`, sorted and with overlapping ranges merged. If the
+    /// whole buffer has been marked dirty, this is a single range covering it.
+    pub fn dirty_ranges(&self) -> Vec<Range<usize>>
+    {
+        let mut ranges = Vec::new();
+
+        if self.all_dirty
+        {
+            ranges.push(0..self.wbuf.len());
+        }
+        else
+        {
+            ranges.extend(self.dirty.iter().cloned());
+            Self::coalesce(&mut ranges);
+        }
+
+        ranges
+    }
+
+    /// Copies the dirty parts of the write buffer into the read buffer, and
+    /// then resets the dirty tracking.
+    pub fn update(&mut self)
+    {
+        if self.all_dirty
+        {
+            self.rbuf.clone_from(&self.wbuf);
+        }
+        else
+        {
+            self.rbuf.truncate(self.wbuf.len());
+            let old_len = self.rbuf.len();
+
+            for range in &self.dirty
+            {
+                let end = cmp::min(range.end, old_len);
+                if range.start < end
+                {
+                    self.rbuf[range.start..end]
+                        .clone_from_slice(&self.wbuf[range.start..end]);
+                }
+            }
+
+            self.rbuf.extend_from_slice(&self.wbuf[old_len..]);
+        }
+
+        self.dirty.clear();
+        self.all_dirty = false;
+        self.coalesce_at = MIN_COALESCE_AT;
+    }
+
+    /// Returns the read buffer, discarding the write buffer.
+    pub fn unbuffer_read(self) -> Vec<T>
+    {
+        self.rbuf
+    }
+
+    /// Returns the write buffer, discarding the read buffer.
+    pub fn unbuffer_write(self) -> Vec<T>
+    {
+        self.wbuf
+    }
+
+    fn mark(dirty: &mut Vec<Range<usize>>, coalesce_at: &mut usize, range: Range<usize>)
+    {
+        if range.start >= range.end
+        {
+            return;
+        }
+
+        // cheap merge for the common case of writing sequentially
+        if let Some(last) = dirty.last_mut()
+        {
+            if range.start <= last.end && last.start <= range.end
+            {
+                last.start = cmp::min(last.start, range.start);
+                last.end = cmp::max(last.end, range.end);
+                return;
+            }
+        }
+
+        dirty.push(range);
+
+        // writes which jump back and forth never touch the last range, so
+        // merge everything once in a while to keep the list from growing
+        // with every write
+        if dirty.len() >= *coalesce_at
+        {
+            Self::coalesce(dirty);
+            *coalesce_at = cmp::max(2 * dirty.len(), MIN_COALESCE_AT);
+        }
+    }
+
+    fn coalesce(ranges: &mut Vec<Range<usize>>)
+    {
+        ranges.sort_by_key(|r| r.start);
+
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges.drain(..)
+        {
+            match merged.last_mut()
+            {
+                Some(last) if range.start <= last.end =>
+                {
+                    last.end = cmp::max(last.end, range.end);
+                }
+
+                _ => merged.push(range),
+            }
+        }
+
+        *ranges = merged;
+    }
+}
+
+impl<T: Clone> Default for DirtyVec<T>
+{
+    fn default() -> Self
+    {
+        Self::new(Vec::new())
+    }
+}
+
+impl<T: Clone> From<Vec<T>> for DirtyVec<T>
+{
+    fn from(value: Vec<T>) -> Self
+    {
+        Self::new(value)
+    }
+}
+
+impl<T: Clone> Deref for DirtyVec<T>
+{
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Vec<T>
+    {
+        self.read()
+    }
+}
+
+impl<T: Clone> DerefMut for DirtyVec<T>
+{
+    /// Behaves like `write`, so marks the whole buffer as dirty.
+    fn deref_mut(&mut self) -> &mut Vec<T>
+    {
+        self.write()
+    }
+}
+
+impl<T: Clone, I: SliceIndex<[T]>> Index<I> for DirtyVec<T>
+{
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &Self::Output
+    {
+        &self.rbuf[index]
+    }
+}
+
+impl<T: Clone> IndexMut<usize> for DirtyVec<T>
+{
+    fn index_mut(&mut self, index: usize) -> &mut T
+    {
+        let elem = &mut self.wbuf[index];
+        Self::mark(&mut self.dirty, &mut self.coalesce_at, index..index + 1);
+        elem
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn sparse_writes()
+    {
+        let mut db = DirtyVec::new(vec![0; 10]);
+        db[1] = 1;
+        db[2] = 2;
+        db[8] = 8;
+        db.write_range(4..=5).copy_from_slice(&[4, 5]);
+
+        assert!(db.dirty_ranges() == vec![1..3, 4..6, 8..9]);
+        assert!(*db == vec![0; 10]);
+
+        db.update();
+        assert!(*db == [0, 1, 2, 0, 4, 5, 0, 0, 8, 0]);
+        assert!(db.dirty_ranges().is_empty());
+    }
+
+    #[test]
+    fn only_dirty_elements_are_copied()
+    {
+        let mut db = DirtyVec::new(vec![0; 4]);
+
+        // sneak a change past the tracking, it should not be committed
+        db.wbuf[0] = 9;
+        db[3] = 3;
+        db.update();
+        assert!(*db == [0, 0, 0, 3]);
+
+        // whereas writing through DerefMut copies everything
+        db.iter_mut().for_each(|x| *x += 1);
+        db.update();
+        assert!(*db == [10, 1, 1, 4]);
+    }
+
+    #[test]
+    fn push_and_pop()
+    {
+        let mut db = DirtyVec::new(vec![1, 2, 3]);
+        db.pop();
+        db.pop();
+        db.push(7);
+        assert!(*db == [1, 2, 3]);
+
+        db.update();
+        assert!(*db == [1, 7]);
+
+        db.push(8);
+        db.push(9);
+        db.update();
+        assert!(*db == [1, 7, 8, 9]);
+    }
+
+    #[test]
+    fn repeated_writes_stay_bounded()
+    {
+        let mut db = DirtyVec::new(vec![0; 10]);
+        for i in 0..10_000
+        {
+            db[1] = i;
+            db[5] = i;
+        }
+
+        assert!(db.dirty.len() <= MIN_COALESCE_AT);
+        assert!(db.dirty_ranges() == vec![1..2, 5..6]);
+
+        db.update();
+        assert!(*db == [0, 9999, 0, 0, 0, 9999, 0, 0, 0, 0]);
+    }
+}
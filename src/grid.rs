@@ -0,0 +1,452 @@
+//! Double-buffered grids, for cellular automata and stencil computations.
+//!
+//! A `DoubleBufferedGrid<T, D>` is a `D`-dimensional grid of cells. Each step
+//! computes every cell's new value from its old value and those of its
+//! neighbours in the read grid, so the order cells are visited in never
+//! matters.
+//!
+//! ```rust
+//! use dubble::DoubleBufferedGrid;
+//! use dubble::grid::{Border, Neighbourhood};
+//!
+//! // heat diffusion along a rod whose ends are held at zero
+//! let mut rod = DoubleBufferedGrid::new([5], 0.0f32).with_border(Border::Constant(0.0));
+//! rod[[2]] = 100.0;
+//! rod.update();
+//!
+//! rod.step(|cell|
+//! {
+//!     let around: f32 = cell.neighbours(Neighbourhood::VonNeumann).sum();
+//!     cell.value() + 0.25 * (around - 2.0 * cell.value())
+//! });
+//!
+//! assert!(rod.read() == [0.0, 25.0, 50.0, 25.0, 0.0]);
+//! ```
+//!
+//! Positions are given as `[usize; D]`, with the first coordinate varying
+//! fastest in memory; for a 2D grid, that's `[x, y]`, and the grid is stored
+//! row by row.
+
+use std::ops::
+{
+    Index,
+    IndexMut
+};
+use std::mem;
+
+/// What neighbours outside of the grid look like.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Border<T>
+{
+    /// The grid wraps around, like a torus.
+    Wrap,
+
+    /// Positions outside of the grid read the nearest cell on its edge.
+    Clamp,
+
+    /// Positions outside of the grid read this value.
+    Constant(T),
+}
+
+/// Which cells count as neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Neighbourhood
+{
+    /// Every cell touching this one, including diagonally; 8 of them in 2D.
+    Moore,
+
+    /// Only the cells sharing a face with this one; 4 of them in 2D.
+    VonNeumann,
+}
+
+/// A double-buffered `D`-dimensional grid.
+///
+/// Reading (`get`, `Index`, `neighbours`) uses the read grid, and writing
+/// (`set`, `IndexMut`, `write`) uses the write grid, like `DoubleBuffered`.
+/// `step` computes the whole write grid from the read grid and commits it.
+pub struct DoubleBufferedGrid<T, const D: usize>
+{
+    rbuf: Vec<T>,
+    wbuf: Vec<T>,
+    dims: [usize; D],
+    border: Border<T>,
+
+    /// Offsets of the neighbours in each neighbourhood.
+    moore: Vec<[isize; D]>,
+    von_neumann: Vec<[isize; D]>,
+}
+
+impl<T: Clone, const D: usize> DoubleBufferedGrid<T, D>
+{
+    /// Creates a grid with the given size in each dimension, with every cell
+    /// set to `value`. The border wraps around; see `with_border`.
+    ///
+    /// # Panics
+    ///
+    /// If any dimension is zero.
+    pub fn new(dims: [usize; D], value: T) -> Self
+    {
+        assert!(dims.iter().all(|&d| d > 0), "grid dimensions must be greater than zero");
+
+        let len = dims.iter().product();
+        let mut moore = Vec::new();
+        let mut von_neumann = Vec::new();
+
+        // every combination of -1, 0 and 1 in each dimension, but the cell itself
+        for i in 0..3usize.pow(D as u32)
+        {
+            let mut offset = [0; D];
+            let mut rest = i;
+            for o in &mut offset
+            {
+                *o = (rest % 3) as isize - 1;
+                rest /= 3;
+            }
+
+            let distance: isize = offset.iter().map(|o| o.abs()).sum();
+            if distance == 1
+            {
+                von_neumann.push(offset);
+            }
+
+            if distance != 0
+            {
+                moore.push(offset);
+            }
+        }
+
+        Self
+        {
+            rbuf: vec![value.clone(); len],
+            wbuf: vec![value; len],
+            dims,
+            border: Border::Wrap,
+            moore,
+            von_neumann,
+        }
+    }
+
+    /// Sets what neighbours outside of the grid look like.
+    pub fn with_border(mut self, border: Border<T>) -> Self
+    {
+        self.border = border;
+        self
+    }
+
+    /// Copies the write grid into the read grid.
+    pub fn update(&mut self)
+    {
+        self.rbuf.clone_from(&self.wbuf);
+    }
+
+    /// Calls `rule` for every cell, with a view of the read grid around it,
+    /// and stores what it returns in the write grid. Then commits the result,
+    /// leaving both grids holding the new generation, like `update`.
+    ///
+    /// Since every cell is overwritten, anything written to the write grid
+    /// beforehand is lost; call `update` first to keep it.
+    pub fn step<F>(&mut self, rule: F)
+        where F: FnMut(GridCell<'_, T, D>) -> T
+    {
+        self.step_swap(rule);
+        self.wbuf.clone_from(&self.rbuf);
+    }
+
+    /// Like `step`, but commits by swapping the two grids rather than
+    /// copying, like `DoubleBuffered::update_swap`. Afterwards the write grid
+    /// holds the *previous* generation, so this suits grids which are only
+    /// ever changed by stepping.
+    pub fn step_swap<F>(&mut self, mut rule: F)
+        where F: FnMut(GridCell<'_, T, D>) -> T
+    {
+        for (index, new) in self.wbuf.iter_mut().enumerate()
+        {
+            let cell = GridCell
+            {
+                pos: position(&self.dims, index),
+                index,
+                rbuf: &self.rbuf,
+                dims: &self.dims,
+                border: &self.border,
+                moore: &self.moore,
+                von_neumann: &self.von_neumann,
+            };
+
+            *new = rule(cell);
+        }
+
+        mem::swap(&mut self.rbuf, &mut self.wbuf);
+    }
+}
+
+impl<T, const D: usize> DoubleBufferedGrid<T, D>
+{
+    /// Returns the size of the grid in each dimension.
+    pub fn dims(&self) -> [usize; D]
+    {
+        self.dims
+    }
+
+    /// Returns the read grid, as a flat slice.
+    pub fn read(&self) -> &[T]
+    {
+        &self.rbuf
+    }
+
+    /// Returns the write grid, as a flat slice.
+    pub fn write(&mut self) -> &mut [T]
+    {
+        &mut self.wbuf
+    }
+
+    /// Returns the cell at `pos` in the read grid, or `None` if it's outside
+    /// of the grid.
+    pub fn get(&self, pos: [usize; D]) -> Option<&T>
+    {
+        index(&self.dims, pos).map(|i| &self.rbuf[i])
+    }
+
+    /// Sets the cell at `pos` in the write grid.
+    ///
+    /// # Panics
+    ///
+    /// If `pos` is outside of the grid.
+    pub fn set(&mut self, pos: [usize; D], value: T)
+    {
+        self[pos] = value;
+    }
+
+    /// Iterates over the neighbours of the cell at `pos` in the read grid.
+    pub fn neighbours(&self, pos: [usize; D], neighbourhood: Neighbourhood) -> impl Iterator<Item = &T>
+    {
+        let index = index(&self.dims, pos).expect("position is outside of the grid");
+        GridCell
+        {
+            pos,
+            index,
+            rbuf: &self.rbuf,
+            dims: &self.dims,
+            border: &self.border,
+            moore: &self.moore,
+            von_neumann: &self.von_neumann,
+        }.neighbours(neighbourhood)
+    }
+}
+
+impl<T, const D: usize> Index<[usize; D]> for DoubleBufferedGrid<T, D>
+{
+    type Output = T;
+
+    fn index(&self, pos: [usize; D]) -> &T
+    {
+        self.get(pos).expect("position is outside of the grid")
+    }
+}
+
+impl<T, const D: usize> IndexMut<[usize; D]> for DoubleBufferedGrid<T, D>
+{
+    fn index_mut(&mut self, pos: [usize; D]) -> &mut T
+    {
+        let i = index(&self.dims, pos).expect("position is outside of the grid");
+        &mut self.wbuf[i]
+    }
+}
+
+/// A cell of a `DoubleBufferedGrid`, and the read grid around it, as seen by
+/// the rule passed to `step`.
+pub struct GridCell<'a, T: 'a, const D: usize>
+{
+    pos: [usize; D],
+    index: usize,
+    rbuf: &'a [T],
+    dims: &'a [usize; D],
+    border: &'a Border<T>,
+    moore: &'a [[isize; D]],
+    von_neumann: &'a [[isize; D]],
+}
+
+impl<'a, T, const D: usize> GridCell<'a, T, D>
+{
+    /// Returns the position of the cell.
+    pub fn pos(&self) -> [usize; D]
+    {
+        self.pos
+    }
+
+    /// Returns the cell's value in the read grid.
+    pub fn value(&self) -> &'a T
+    {
+        &self.rbuf[self.index]
+    }
+
+    /// Returns the value of the cell at `offset` from this one, applying the
+    /// grid's border if that's outside of it.
+    pub fn offset(&self, offset: [isize; D]) -> &'a T
+    {
+        let mut pos = [0; D];
+        for i in 0..D
+        {
+            let dim = self.dims[i] as isize;
+            let p = self.pos[i] as isize + offset[i];
+
+            pos[i] = if 0 <= p && p < dim
+            {
+                p as usize
+            }
+            else
+            {
+                match *self.border
+                {
+                    Border::Wrap            => p.rem_euclid(dim) as usize,
+                    Border::Clamp           => p.clamp(0, dim - 1) as usize,
+                    Border::Constant(ref c) => return c,
+                }
+            };
+        }
+
+        &self.rbuf[index(self.dims, pos).unwrap()]
+    }
+
+    /// Iterates over the values of the cell's neighbours.
+    pub fn neighbours(&self, neighbourhood: Neighbourhood) -> impl Iterator<Item = &'a T>
+    {
+        let offsets = match neighbourhood
+        {
+            Neighbourhood::Moore      => self.moore,
+            Neighbourhood::VonNeumann => self.von_neumann,
+        };
+
+        let cell = *self;
+        offsets.iter().map(move |&o| cell.offset(o))
+    }
+}
+
+impl<'a, T, const D: usize> Clone for GridCell<'a, T, D>
+{
+    fn clone(&self) -> Self
+    {
+        *self
+    }
+}
+
+impl<'a, T, const D: usize> Copy for GridCell<'a, T, D> {}
+
+fn index<const D: usize>(dims: &[usize; D], pos: [usize; D]) -> Option<usize>
+{
+    let mut index = 0;
+    for i in (0..D).rev()
+    {
+        if pos[i] >= dims[i]
+        {
+            return None;
+        }
+
+        index = index * dims[i] + pos[i];
+    }
+
+    Some(index)
+}
+
+fn position<const D: usize>(dims: &[usize; D], mut index: usize) -> [usize; D]
+{
+    let mut pos = [0; D];
+    for i in 0..D
+    {
+        pos[i] = index % dims[i];
+        index /= dims[i];
+    }
+
+    pos
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn life(cell: GridCell<'_, bool, 2>) -> bool
+    {
+        let alive = cell.neighbours(Neighbourhood::Moore).filter(|&&x| x).count();
+        alive == 3 || (alive == 2 && *cell.value())
+    }
+
+    #[test]
+    fn glider_wraps_around()
+    {
+        let mut grid = DoubleBufferedGrid::new([6, 6], false);
+        for &pos in &[[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]]
+        {
+            grid[pos] = true;
+        }
+
+        grid.update();
+        let start = grid.read().to_vec();
+
+        // a glider moves one cell diagonally every four generations
+        for _ in 0..4 * 6
+        {
+            grid.step(life);
+        }
+
+        assert!(grid.read() == &start[..]);
+    }
+
+    #[test]
+    fn borders()
+    {
+        let mut grid = DoubleBufferedGrid::new([3, 2], 0).with_border(Border::Clamp);
+        for (i, x) in grid.write().iter_mut().enumerate()
+        {
+            *x = i;
+        }
+
+        grid.update();
+        assert!(grid[[2, 1]] == 5);
+        assert!(grid.get([3, 0]).is_none());
+
+        let mut around: Vec<_> = grid.neighbours([0, 0], Neighbourhood::VonNeumann).cloned().collect();
+        around.sort();
+        assert!(around == [0, 0, 1, 3]);
+
+        let mut grid = grid.with_border(Border::Wrap);
+        around = grid.neighbours([0, 0], Neighbourhood::VonNeumann).cloned().collect();
+        around.sort();
+        assert!(around == [1, 2, 3, 3]);
+
+        grid = grid.with_border(Border::Constant(9));
+        assert!(grid.neighbours([1, 1], Neighbourhood::Moore).filter(|&&x| x == 9).count() == 3);
+    }
+
+    #[test]
+    fn three_dimensions()
+    {
+        let mut grid = DoubleBufferedGrid::new([3, 3, 3], 0u32).with_border(Border::Constant(0));
+        grid.set([1, 1, 1], 1);
+        grid.update();
+
+        assert!(grid.neighbours([0, 0, 0], Neighbourhood::Moore).count() == 26);
+        assert!(grid.neighbours([0, 0, 0], Neighbourhood::VonNeumann).count() == 6);
+
+        grid.step(|cell| cell.neighbours(Neighbourhood::VonNeumann).sum());
+        assert!(grid[[1, 1, 1]] == 0);
+        assert!(grid[[1, 1, 0]] == 1 && grid[[0, 1, 1]] == 1);
+        assert!(grid.read().iter().sum::<u32>() == 6);
+    }
+
+    #[test]
+    fn writing_after_a_step()
+    {
+        let mut grid = DoubleBufferedGrid::new([3], 1);
+        grid.step(|cell| cell.value() + 1);
+        assert!(grid.read() == [2, 2, 2]);
+
+        grid[[0]] = 100;
+        grid.update();
+        assert!(grid.read() == [100, 2, 2]);
+
+        // swapping leaves the write grid a generation behind
+        grid.step_swap(|cell| cell.value() + 1);
+        assert!(grid.read() == [101, 3, 3]);
+        assert!(grid.write() == [100, 2, 2]);
+    }
+}
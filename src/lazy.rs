@@ -0,0 +1,275 @@
+//! A double-buffer which puts off copying until the read buffer is used.
+
+use std::cell::
+{
+    Cell,
+    UnsafeCell
+};
+use std::mem;
+use std::ops::
+{
+    Deref,
+    DerefMut,
+    Index,
+    IndexMut
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State
+{
+    /// The read buffer holds the latest commit.
+    Fresh,
+
+    /// The write buffer has been committed, but not copied yet.
+    Stale,
+
+    /// The write buffer is being copied into the read buffer.
+    Copying,
+}
+
+/// A double-buffer where `update` only marks the read buffer as out of date,
+/// and the write buffer is copied into it the next time it's needed.
+///
+/// Any number of commits with nothing in between cost a single copy, made by
+/// the first `read` after them. Writing also has to bring the read buffer up
+/// to date first, since it would otherwise change what was committed; the
+/// exception is `set`, which replaces the whole value, and so can move the
+/// committed value into the read buffer instead of cloning it.
+///
+/// This suits a buffer which is committed every tick, but read rarely, and
+/// whose new value is computed from scratch:
+///
+/// ```rust
+/// use dubble::LazyBuffered;
+///
+/// let mut stats = LazyBuffered::new(vec![0u64; 1024]);
+///
+/// for tick in 1..=60
+/// {
+///     // no copying happens in here
+///     stats.set(vec![tick; 1024]);
+///     stats.update();
+/// }
+///
+/// // one copy, when the UI looks at the stats
+/// assert!(stats[0] == 60);
+/// ```
+///
+/// Since reading can write to the read buffer, a `LazyBuffered` can't be
+/// shared between threads.
+pub struct LazyBuffered<T: Clone>
+{
+    rbuf: UnsafeCell<T>,
+    wbuf: T,
+    state: Cell<State>,
+}
+
+impl<T: Clone> LazyBuffered<T>
+{
+    /// Initialises both buffers with `value`.
+    pub fn new(value: T) -> Self
+    {
+        Self
+        {
+            rbuf: UnsafeCell::new(value.clone()),
+            wbuf: value,
+            state: Cell::new(State::Fresh),
+        }
+    }
+
+    /// Returns an immutable reference to the read buffer, first copying the
+    /// write buffer into it if it has been committed since.
+    pub fn read(&self) -> &T
+    {
+        match self.state.get()
+        {
+            State::Fresh   => {}
+            State::Copying => panic!("LazyBuffered was read while being copied into"),
+            State::Stale   =>
+            {
+                self.state.set(State::Copying);
+
+                // the state only becomes stale through `&mut self`, which
+                // means no references into the read buffer were alive then,
+                // and only the first read since has got this far
+                unsafe { (*self.rbuf.get()).clone_from(&self.wbuf) };
+                self.state.set(State::Fresh);
+            }
+        }
+
+        unsafe { &*self.rbuf.get() }
+    }
+
+    /// Returns a mutable reference to the write buffer. If the buffer has been
+    /// committed since it was last copied, it is copied first.
+    pub fn write(&mut self) -> &mut T
+    {
+        if self.is_stale()
+        {
+            self.rbuf.get_mut().clone_from(&self.wbuf);
+            self.state.set(State::Fresh);
+        }
+
+        &mut self.wbuf
+    }
+
+    /// Replaces the contents of the write buffer. Unlike `write`, this never
+    /// copies: if the buffer has been committed since it was last copied, the
+    /// committed value is moved into the read buffer instead.
+    pub fn set(&mut self, value: T)
+    {
+        if self.is_stale()
+        {
+            mem::swap(self.rbuf.get_mut(), &mut self.wbuf);
+            self.state.set(State::Fresh);
+        }
+
+        self.wbuf = value;
+    }
+
+    /// Commits the write buffer. The copy into the read buffer is put off
+    /// until it is needed.
+    pub fn update(&mut self)
+    {
+        self.state.set(State::Stale);
+    }
+
+    /// Returns whether the buffer has been committed without being copied
+    /// yet.
+    pub fn is_stale(&self) -> bool
+    {
+        self.state.get() != State::Fresh
+    }
+
+    /// Returns the read buffer, discarding the write buffer.
+    pub fn unbuffer_read(self) -> T
+    {
+        if self.is_stale()
+        {
+            return self.wbuf;
+        }
+
+        self.rbuf.into_inner()
+    }
+
+    /// Returns the write buffer, discarding the read buffer.
+    pub fn unbuffer_write(self) -> T
+    {
+        self.wbuf
+    }
+}
+
+impl<T: Clone + Default> Default for LazyBuffered<T>
+{
+    fn default() -> Self
+    {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Clone> Deref for LazyBuffered<T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &T
+    {
+        self.read()
+    }
+}
+
+impl<T: Clone> DerefMut for LazyBuffered<T>
+{
+    fn deref_mut(&mut self) -> &mut T
+    {
+        self.write()
+    }
+}
+
+impl<I, T: Clone + Index<I>> Index<I> for LazyBuffered<T>
+{
+    type Output = T::Output;
+
+    fn index(&self, index: I) -> &Self::Output
+    {
+        &self.read()[index]
+    }
+}
+
+impl<I, T: Clone + IndexMut<I>> IndexMut<I> for LazyBuffered<T>
+{
+    fn index_mut(&mut self, index: I) -> &mut Self::Output
+    {
+        &mut self.write()[index]
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::rc::Rc;
+
+    /// Counts how many times it has been cloned.
+    struct Counted(Rc<Cell<usize>>, u32);
+
+    impl Clone for Counted
+    {
+        fn clone(&self) -> Self
+        {
+            self.0.set(self.0.get() + 1);
+            Counted(self.0.clone(), self.1)
+        }
+    }
+
+    #[test]
+    fn commits_collapse_into_one_copy()
+    {
+        let clones = Rc::new(Cell::new(0));
+        let mut lb = LazyBuffered::new(Counted(clones.clone(), 0));
+        clones.set(0);
+
+        for i in 1..10
+        {
+            lb.set(Counted(clones.clone(), i));
+            lb.update();
+        }
+
+        assert!(clones.get() == 0);
+        assert!(lb.is_stale());
+        assert!(lb.read().1 == 9);
+        assert!(lb.read().1 == 9);
+        assert!(clones.get() == 1);
+    }
+
+    #[test]
+    fn writes_see_the_committed_value()
+    {
+        let mut lb = LazyBuffered::new(vec![1]);
+        lb.push(2);
+        lb.update();
+
+        // writing after a commit mustn't change what was committed
+        lb.push(3);
+        assert!(*lb == [1, 2]);
+        assert!(*lb.write() == [1, 2, 3]);
+
+        lb.update();
+        lb.update();
+        assert!(lb[2] == 3);
+        assert!(lb.unbuffer_read() == [1, 2, 3]);
+    }
+
+    #[test]
+    fn set_after_commit()
+    {
+        let mut lb = LazyBuffered::new(String::from("a"));
+        lb.set(String::from("b"));
+        lb.update();
+        lb.set(String::from("c"));
+
+        assert!(!lb.is_stale());
+        assert!(*lb == "b");
+        lb.update();
+        assert!(lb.unbuffer_read() == "c");
+    }
+}
@@ -0,0 +1,193 @@
+//! Interpolating between committed states.
+//!
+//! A game with a fixed simulation timestep usually renders somewhere between
+//! two simulation steps. To do that, it needs both the previous and the
+//! latest committed state, and a way to blend between them. `Lerp` provides
+//! the blending; `DoubleBuffered::with_previous` keeps the previous state
+//! around, and `interpolate` puts them together.
+//!
+//! ```rust
+//! use dubble::DoubleBuffered;
+//!
+//! let mut pos = DoubleBuffered::new((0.0f32, 10.0f32)).with_previous();
+//!
+//! *pos = (1.0, 20.0);
+//! pos.update();
+//!
+//! // a quarter of the way between the last two simulation steps
+//! assert!(pos.interpolate(0.25) == (0.25, 12.5));
+//! ```
+
+use DoubleBuffered;
+use MultiBuffered;
+
+/// Linear interpolation.
+pub trait Lerp
+{
+    /// Returns the value `alpha` of the way from `self` to `to`, so that an
+    /// `alpha` of `0.0` gives `self` and `1.0` gives `to`.
+    fn lerp(&self, to: &Self, alpha: f32) -> Self;
+}
+
+impl Lerp for f32
+{
+    fn lerp(&self, to: &Self, alpha: f32) -> Self
+    {
+        self + (to - self) * alpha
+    }
+}
+
+impl Lerp for f64
+{
+    fn lerp(&self, to: &Self, alpha: f32) -> Self
+    {
+        self + (to - self) * f64::from(alpha)
+    }
+}
+
+impl<T: Lerp, const N: usize> Lerp for [T; N]
+{
+    fn lerp(&self, to: &Self, alpha: f32) -> Self
+    {
+        ::std::array::from_fn(|i| self[i].lerp(&to[i], alpha))
+    }
+}
+
+macro_rules! impl_lerp_tuple
+{
+    ($($name:ident $idx:tt)+) =>
+    {
+        impl<$($name: Lerp),+> Lerp for ($($name,)+)
+        {
+            fn lerp(&self, to: &Self, alpha: f32) -> Self
+            {
+                ($(self.$idx.lerp(&to.$idx, alpha),)+)
+            }
+        }
+    };
+}
+
+impl_lerp_tuple!(A 0);
+impl_lerp_tuple!(A 0 B 1);
+impl_lerp_tuple!(A 0 B 1 C 2);
+impl_lerp_tuple!(A 0 B 1 C 2 D 3);
+impl_lerp_tuple!(A 0 B 1 C 2 D 3 E 4);
+impl_lerp_tuple!(A 0 B 1 C 2 D 3 E 4 F 5);
+impl_lerp_tuple!(A 0 B 1 C 2 D 3 E 4 F 5 G 6);
+impl_lerp_tuple!(A 0 B 1 C 2 D 3 E 4 F 5 G 6 H 7);
+impl_lerp_tuple!(A 0 B 1 C 2 D 3 E 4 F 5 G 6 H 7 I 8);
+impl_lerp_tuple!(A 0 B 1 C 2 D 3 E 4 F 5 G 6 H 7 I 8 J 9);
+impl_lerp_tuple!(A 0 B 1 C 2 D 3 E 4 F 5 G 6 H 7 I 8 J 9 K 10);
+impl_lerp_tuple!(A 0 B 1 C 2 D 3 E 4 F 5 G 6 H 7 I 8 J 9 K 10 L 11);
+
+impl<T: Lerp, S> DoubleBuffered<T, S>
+{
+    /// Blends the previous committed state with the latest one; see
+    /// `with_previous`. An `alpha` of `0.0` gives the previous state, and
+    /// `1.0` the latest.
+    ///
+    /// If the buffer isn't keeping its previous state, this always returns
+    /// the latest state.
+    pub fn interpolate(&self, alpha: f32) -> T
+    {
+        self.previous().unwrap_or(&self.rbuf).lerp(&self.rbuf, alpha)
+    }
+}
+
+impl<T: Lerp + Clone, const N: usize> MultiBuffered<T, N>
+{
+    /// Blends the previous committed state with the latest one. An `alpha`
+    /// of `0.0` gives `read_back(1)`, and `1.0` gives `read()`.
+    ///
+    /// If only one state is kept, this always returns the latest state.
+    pub fn interpolate(&self, alpha: f32) -> T
+    {
+        let from = if N > 1 { self.read_back(1) } else { self.read() };
+        from.lerp(self.read(), alpha)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn lerp()
+    {
+        assert!(2.0f32.lerp(&4.0, 0.5) == 3.0);
+        assert!(2.0f64.lerp(&4.0, 0.0) == 2.0);
+        assert!([0.0f32, 10.0].lerp(&[1.0, 20.0], 1.0) == [1.0, 20.0]);
+        assert!((0.0f32, [0.0f64; 2]).lerp(&(2.0, [4.0; 2]), 0.5) == (1.0, [2.0; 2]));
+    }
+
+    #[test]
+    fn previous_state_is_kept()
+    {
+        let mut db = DoubleBuffered::new(0.0f32);
+
+        // not keeping the previous state, so nothing to blend with
+        *db = 1.0;
+        db.update();
+        assert!(db.previous().is_none());
+        assert!(db.interpolate(0.5) == 1.0);
+
+        let mut db = db.with_previous();
+        for x in 2..5
+        {
+            *db = x as f32;
+            db.update();
+        }
+
+        assert!(db.previous() == Some(&3.0));
+        assert!(db.interpolate(0.5) == 3.5);
+
+        // swapping rotates the previous state as well
+        *db = 10.0;
+        db.update_swap();
+        assert!(db.previous() == Some(&4.0));
+        assert!(*db == 10.0);
+        assert!(*db.write() == 4.0);
+        assert!(db.interpolate(0.5) == 7.0);
+    }
+
+    #[test]
+    fn skipped_commit_stops_moving()
+    {
+        let mut pos = DoubleBuffered::new(0.0f32).with_previous();
+        *pos = 1.0;
+        assert!(pos.update_if_changed());
+        assert!(pos.interpolate(0.5) == 0.5);
+
+        for _ in 0..3
+        {
+            assert!(!pos.update_if_changed());
+            assert!(pos.previous() == Some(&1.0));
+            assert!(pos.interpolate(0.5) == 1.0);
+        }
+
+        // and it picks up again from where it stopped
+        *pos = 3.0;
+        pos.update_if_changed();
+        assert!(pos.previous() == Some(&1.0));
+        assert!(pos.interpolate(0.5) == 2.0);
+
+        *pos = 5.0;
+        pos.update_swap();
+        assert!(pos.previous() == Some(&3.0));
+        assert!(*pos.write() == 3.0);
+    }
+
+    #[test]
+    fn multi()
+    {
+        let mut mb = MultiBuffered::<f64, 3>::new(0.0);
+        mb.upsert(1.0);
+        mb.upsert(2.0);
+        assert!(mb.interpolate(0.25) == 1.25);
+
+        let mut mb = MultiBuffered::<f64, 1>::new(0.0);
+        mb.upsert(1.0);
+        assert!(mb.interpolate(0.25) == 1.0);
+    }
+}
@@ -84,7 +84,196 @@
 //! In other words, `Deref` behaves as if you had called `my_buf.read()`, and
 //! `DerefMut` behaves as if you had called `my_buf.write()`.
 //!
+//! ## Reading and writing at the same time
+//!
+//! `step` passes the read buffer and the write buffer to a closure together,
+//! and for slice-like buffers `step_elements` iterates over each element's
+//! old value, the whole old slice and its new value. Since the old state is
+//! only ever borrowed immutably, the order elements are updated in can't
+//! matter.
+//!
+//! `par_step` and `par_step_chunks` do the same on several threads at once,
+//! each writing to its own chunk of the write buffer while reading all of the
+//! read buffer. With the `rayon` feature enabled, they run on rayon's thread
+//! pool. For large buffers, `par_update` commits on several threads as well.
+//!
+//! ## Grids
+//!
+//! `DoubleBufferedGrid` is a grid of any number of dimensions, for cellular
+//! automata and stencil computations. Its `step` computes every cell from its
+//! neighbours in the read grid; see the `grid` module.
+//!
+//! ## Describing changes
+//!
+//! For types which implement `Diff`, `pending_diff` describes how the write
+//! buffer differs from the read buffer, and `apply_diff` replays such a
+//! change onto another buffer. See the `diff` module.
+//!
+//! ## Transactions
+//!
+//! `transaction` returns a guard which dereferences to the write buffer. The
+//! writes are committed if you call `commit` on it, and thrown away if it is
+//! dropped without being committed, for example because of a panic.
+//!
+//! ## Commit strategies
+//!
+//! By default `update` clones the write buffer into the read buffer. The
+//! second type parameter of `DoubleBuffered` can be used to pick a different
+//! `CommitStrategy`, for example one which reuses the read buffer's
+//! allocation. See the `strategy` module for details.
+//!
+//! ## Adding and removing elements
+//!
+//! Pushing to or removing from a `DoubleBuffered<Vec<T>>` makes indices
+//! disagree between the read and write buffers until the next update.
+//! `DoubleBufferedVec` queues insertions and removals until `update` instead,
+//! and hands out handles which keep referring to the same element.
+//!
+//! ## Buffers which are rarely read
+//!
+//! `LazyBuffered` puts off the copy made by `update` until the read buffer is
+//! next used, so that a buffer which is committed often but read rarely only
+//! pays for the commits which are actually seen.
+//!
+//! ## Adding up contributions
+//!
+//! `AccumulatingBuffered` collects many contributions each frame, such as
+//! forces or damage, and combines them with its type's `Monoid`
+//! implementation. `update` publishes the total and starts the write side
+//! again from nothing, rather than carrying it over.
+//!
+//! ## Keeping older states
+//!
+//! `MultiBuffered<T, N>` keeps the last `N` committed states instead of just
+//! the latest, which is handy for things like verlet integration or temporal
+//! filters; `read_back(k)` returns the state from `k` updates ago.
+//!
+//! ## Holding on to a committed state
+//!
+//! The reference returned by `read` only lives as long as the borrow of the
+//! buffer. `SnapshotBuffered` keeps its read buffer in an `Arc` instead, and
+//! `snapshot` hands out clones of it which stay valid across later updates.
+//!
+//! ## Interpolation
+//!
+//! A buffer created `with_previous` keeps the state from before the last
+//! commit as well as the latest one, and for types which implement `Lerp`,
+//! `interpolate` blends between the two. This is what a game with a fixed
+//! simulation timestep needs to render between steps.
+//!
+//! ## Threading
+//!
+//! `DoubleBuffered` can be sent between threads, but its contents can't be
+//! read from one thread while another writes to it. `split` turns it into a
+//! `sync::WriteHandle` and any number of `sync::ReadHandle`s which can; see
+//! the `sync` module.
+//!
+//! If a writer thread and a reader thread shouldn't have to wait for each
+//! other at all, use a `TripleBuffered` instead; see the `triple` module.
+//!
+//! For small `Copy` values, `SeqDoubleBuffered` lets any number of threads
+//! read the committed value without locking; see the `seqlock` module.
+//!
+//! ## Updating lots of buffers at once
+//!
+//! Every buffer in this crate implements `Updatable`, as do tuples, arrays,
+//! `Vec`s and `Option`s of them, so that groups of buffers can be committed
+//! with a single `update`. `BufferSet` gathers up buffers of different types
+//! so that they can be committed together.
+//!
+//! ## Double-buffering every field of a struct
+//!
+//! With the `derive` feature enabled, `#[derive(DoubleBuffer)]` generates a
+//! copy of a struct in which every field is a `DoubleBuffered`, along with
+//! accessors for each field and a single `update` which commits all of them.
+//!
+//! ## Serialization
+//!
+//! With the `serde` feature enabled, `DoubleBuffered` implements `Serialize`
+//! and `Deserialize`. See the `serialize` module for how to save only the
+//! committed state rather than both buffers.
+//!
+//! ## Sparse writes to large `Vec`s
+//!
+//! `DirtyVec` is a double-buffered `Vec` which remembers which elements were
+//! written, so that `update` only copies those instead of the whole `Vec`.
+//! `DoubleBufferedMap` does the same for a `HashMap`, keeping track of which
+//! keys were inserted, removed or written to.
+//!
+//! ## Huge, sparsely edited state
+//!
+//! `OverlayBuffered` doesn't keep a second copy at all. Writing to an element
+//! copies it into an overlay on top of the read side, and `update` moves the
+//! edited elements back in, so the write side only takes up as much memory as
+//! the edits.
+//!
 
+#[cfg(feature = "derive")]
+extern crate dubble_derive;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+
+pub mod diff;
+pub mod grid;
+pub mod strategy;
+pub mod seqlock;
+pub mod sync;
+pub mod triple;
+#[cfg(feature = "serde")]
+pub mod serialize;
+mod accumulate;
+mod buffered_map;
+mod buffered_vec;
+mod dirty_vec;
+mod lazy;
+mod lerp;
+mod multi;
+mod overlay;
+mod parallel;
+mod snapshot;
+mod step;
+mod transaction;
+mod updatable;
+
+pub use accumulate::
+{
+    AccumulatingBuffered,
+    Monoid
+};
+pub use buffered_map::DoubleBufferedMap;
+pub use buffered_vec::
+{
+    DoubleBufferedVec,
+    VecHandle
+};
+pub use diff::Diff;
+pub use strategy::CommitStrategy;
+pub use dirty_vec::DirtyVec;
+pub use grid::DoubleBufferedGrid;
+pub use lazy::LazyBuffered;
+pub use lerp::Lerp;
+pub use multi::MultiBuffered;
+pub use overlay::OverlayBuffered;
+pub use seqlock::SeqDoubleBuffered;
+pub use snapshot::SnapshotBuffered;
+pub use step::StepElements;
+pub use transaction::Transaction;
+pub use updatable::
+{
+    BufferSet,
+    Updatable
+};
+
+#[cfg(feature = "derive")]
+pub use dubble_derive::DoubleBuffer;
+pub use triple::TripleBuffered;
+
+use std::marker::PhantomData;
+use std::mem;
 use std::ops::
 {
     Deref,
@@ -93,15 +282,47 @@ use std::ops::
     IndexMut
 };
 
-/// Represents something that is double-buffered. The type being buffered must
-/// be `Clone`, so that the read buffer can be updated with the contents of the
-/// write buffer during the update.
+use strategy::CloneCommit;
+
+/// Represents something that is double-buffered. With the default commit
+/// strategy, the type being buffered must be `Clone`, so that the read buffer
+/// can be updated with the contents of the write buffer during the update.
+///
+/// `S` decides how `update` commits the write buffer; see the `strategy`
+/// module for the alternatives.
 ///
 /// See the module-level documentation for more information.
-pub struct DoubleBuffered<T: Clone>
+pub struct DoubleBuffered<T, S = CloneCommit>
 {
     rbuf: T,
     wbuf: T,
+
+    /// Whether the write buffer has been handed out mutably since the last
+    /// commit.
+    dirty: bool,
+
+    /// Number of commits so far.
+    generation: u64,
+
+    /// The read buffer from before the last commit, if `with_previous` was
+    /// used.
+    prev: Option<Previous<T>>,
+    strategy: PhantomData<S>,
+}
+
+/// The state kept by a buffer created `with_previous`.
+struct Previous<T>
+{
+    value: Box<T>,
+
+    /// `T::clone_from`, which swapping commits need to fill in the previous
+    /// state, but which they can't name since they don't require `T: Clone`.
+    clone_from: fn(&mut T, &T),
+
+    /// Set when `update_if_changed` skips a commit. Nothing changed in that
+    /// step, so the previous state is the read buffer, and `value` is out of
+    /// date until the next commit replaces it.
+    skipped: bool,
 }
 
 impl<T: Clone> DoubleBuffered<T>
@@ -110,23 +331,95 @@ impl<T: Clone> DoubleBuffered<T>
     /// with the same value.
     pub fn new(value: T) -> Self
     {
-        Self
-        {
-            rbuf: value.clone(),
-            wbuf: value.clone(),
-        }
+        Self::from_buffers(value.clone(), value)
     }
+}
 
+impl<T> DoubleBuffered<T>
+{
     /// Uses `constructor` to construct each buffer. It's handy to pass things
     /// like `Vec::new` into here. `DoubleBuffered` also implements default
     /// if the wrapped type does, so you could also do
     /// `DoubleBuffered<Vec<T>>::default()`
     pub fn construct_with<F: Fn() -> T>(constructor: F) -> Self
+    {
+        Self::from_buffers(constructor(), constructor())
+    }
+}
+
+impl<T, S> DoubleBuffered<T, S>
+{
+    fn from_buffers(rbuf: T, wbuf: T) -> Self
     {
         Self
         {
-            rbuf: constructor(),
-            wbuf: constructor(),
+            rbuf,
+            wbuf,
+            dirty: false,
+            generation: 0,
+            prev: None,
+            strategy: PhantomData,
+        }
+    }
+
+    /// If the previous state is being kept, moves the read buffer into it. The
+    /// read buffer is left holding the state from two commits ago, ready to
+    /// be overwritten.
+    fn rotate_previous(&mut self)
+    {
+        if let Some(ref mut prev) = self.prev
+        {
+            mem::swap(&mut *prev.value, &mut self.rbuf);
+        }
+    }
+
+    /// If the previous state is being kept, copies the write buffer into it.
+    /// After a swapping commit, the write buffer holds the old read buffer,
+    /// which is needed in both places.
+    fn copy_previous_from_write(&mut self)
+    {
+        if let Some(ref mut prev) = self.prev
+        {
+            (prev.clone_from)(&mut prev.value, &self.wbuf);
+        }
+    }
+
+    /// Marks a commit as having happened.
+    fn committed(&mut self)
+    {
+        self.dirty = false;
+        self.generation += 1;
+
+        if let Some(ref mut prev) = self.prev
+        {
+            prev.skipped = false;
+        }
+    }
+
+    fn into_buffers(self) -> (T, T)
+    {
+        (self.rbuf, self.wbuf)
+    }
+
+    /// Changes the commit strategy used by `update`. The contents of both
+    /// buffers are kept as they are.
+    ///
+    /// ```rust
+    /// # use dubble::DoubleBuffered;
+    /// use dubble::strategy::CloneFromCommit;
+    /// let mut my_buf = DoubleBuffered::new(String::from("hello"))
+    ///     .with_strategy::<CloneFromCommit>();
+    /// ```
+    pub fn with_strategy<U: CommitStrategy<T>>(self) -> DoubleBuffered<T, U>
+    {
+        DoubleBuffered
+        {
+            rbuf: self.rbuf,
+            wbuf: self.wbuf,
+            dirty: self.dirty,
+            generation: self.generation,
+            prev: self.prev,
+            strategy: PhantomData,
         }
     }
 
@@ -136,6 +429,13 @@ impl<T: Clone> DoubleBuffered<T>
         &self.rbuf
     }
 
+    /// Returns the read buffer as it was before the last commit, if the
+    /// buffer was created `with_previous`.
+    pub fn previous(&self) -> Option<&T>
+    {
+        self.prev.as_ref().map(|prev| if prev.skipped { &self.rbuf } else { &*prev.value })
+    }
+
     /// Returns a mutable reference to the write buffer.
     /// Note that changes made through this reference will not be reflected
     /// until after `update` is called.
@@ -162,23 +462,99 @@ impl<T: Clone> DoubleBuffered<T>
     /// ```
     ///
     /// Notice that you have to create a copy and modify it.
+    ///
+    /// Calling this (or using `DerefMut` or `IndexMut`, which call it) marks
+    /// the buffer as having pending changes, whether or not anything is
+    /// actually written through the reference.
     pub fn write(&mut self) -> &mut T
     {
+        self.dirty = true;
         &mut self.wbuf
     }
 
-    /// Copies the write buffer into the read buffer.
-    pub fn update(&mut self)
+    /// Returns whether the write buffer has been accessed mutably since the
+    /// last commit.
+    ///
+    /// ```rust
+    /// # use dubble::DoubleBuffered;
+    /// let mut my_buf = DoubleBuffered::new(0);
+    /// assert!(!my_buf.has_pending_changes());
+    ///
+    /// *my_buf = 1;
+    /// assert!(my_buf.has_pending_changes());
+    ///
+    /// my_buf.update();
+    /// assert!(!my_buf.has_pending_changes());
+    /// ```
+    pub fn has_pending_changes(&self) -> bool
     {
-        self.rbuf = self.wbuf.clone();
+        self.dirty
     }
 
-    /// Writes the value to the write buffer, and then immediately updates the
-    /// read buffer.
-    pub fn upsert(&mut self, value: T)
+    /// Returns the number of times the buffer has been committed. Remember
+    /// the value, and compare it later to find out whether the read buffer
+    /// may have changed in the meantime.
+    ///
+    /// ```rust
+    /// # use dubble::DoubleBuffered;
+    /// let mut my_buf = DoubleBuffered::new(0);
+    /// let seen = my_buf.generation();
+    ///
+    /// // nothing was written, so nothing is committed
+    /// my_buf.update_if_changed();
+    /// assert!(my_buf.generation() == seen);
+    ///
+    /// *my_buf = 1;
+    /// my_buf.update_if_changed();
+    /// assert!(my_buf.generation() != seen);
+    /// ```
+    pub fn generation(&self) -> u64
     {
-        *self.write() = value;
-        self.update();
+        self.generation
+    }
+
+    /// Commits the write buffer by swapping it with the read buffer, instead of
+    /// cloning it.
+    ///
+    /// This is much cheaper than `update` for large buffers, but note that
+    /// afterwards the write buffer holds the *previous* contents of the read
+    /// buffer, not a copy of what was just committed. Use this when every step
+    /// fully overwrites the write buffer anyway.
+    ///
+    /// If the buffer was created `with_previous`, the old read buffer is also
+    /// cloned into the previous state, so the swap is no longer free.
+    ///
+    /// ```rust
+    /// # use dubble::DoubleBuffered;
+    /// let mut my_buf = DoubleBuffered::new(1);
+    /// *my_buf.write() = 2;
+    /// my_buf.update_swap();
+    /// assert!(*my_buf.read() == 2);
+    /// assert!(*my_buf.write() == 1);
+    /// ```
+    pub fn update_swap(&mut self)
+    {
+        mem::swap(&mut self.rbuf, &mut self.wbuf);
+        self.copy_previous_from_write();
+        self.committed();
+    }
+
+    /// Like `update_swap`, but afterwards calls `reseed` with the newly
+    /// committed read buffer and the new write buffer, so that the write
+    /// buffer can be brought back into a known state.
+    ///
+    /// ```rust
+    /// # use dubble::DoubleBuffered;
+    /// let mut my_buf = DoubleBuffered::new(vec![0; 4]);
+    /// my_buf.write()[0] = 1;
+    /// my_buf.update_swap_with(|r, w| w.clone_from(r));
+    /// assert!(my_buf.read() == &[1, 0, 0, 0]);
+    /// assert!(my_buf.write() == &[1, 0, 0, 0]);
+    /// ```
+    pub fn update_swap_with<F: FnOnce(&T, &mut T)>(&mut self, reseed: F)
+    {
+        self.update_swap();
+        reseed(&self.rbuf, &mut self.wbuf);
     }
 
     /// Returns the read buffer. This does not update the read buffer with the
@@ -196,7 +572,154 @@ impl<T: Clone> DoubleBuffered<T>
     }
 }
 
-impl<T: Clone> Deref for DoubleBuffered<T>
+impl<T, S: CommitStrategy<T>> DoubleBuffered<T, S>
+{
+    /// Commits the write buffer to the read buffer. With the default strategy
+    /// this copies the write buffer into the read buffer.
+    pub fn update(&mut self)
+    {
+        if S::SWAPS
+        {
+            S::commit(&mut self.rbuf, &mut self.wbuf);
+            self.copy_previous_from_write();
+        }
+        else
+        {
+            self.rotate_previous();
+            S::commit(&mut self.rbuf, &mut self.wbuf);
+        }
+
+        self.committed();
+    }
+
+    /// Commits the write buffer, but only if it has pending changes; see
+    /// `has_pending_changes`. Returns whether anything was committed.
+    ///
+    /// A skipped commit still counts as a step for `previous`, which becomes
+    /// the same as the read buffer, so that a buffer which stopped changing
+    /// doesn't interpolate as if it were still moving.
+    pub fn update_if_changed(&mut self) -> bool
+    {
+        if !self.dirty
+        {
+            if let Some(ref mut prev) = self.prev
+            {
+                prev.skipped = true;
+            }
+
+            return false;
+        }
+
+        self.update();
+        true
+    }
+
+    /// Writes the value to the write buffer, and then immediately updates the
+    /// read buffer.
+    pub fn upsert(&mut self, value: T)
+    {
+        *self.write() = value;
+        self.update();
+    }
+}
+
+impl<T: Clone, S> DoubleBuffered<T, S>
+{
+    /// Makes the buffer keep hold of the previous committed state, as well as
+    /// the latest one, so that the two can be blended with `interpolate`.
+    /// The previous state starts off as a copy of the read buffer.
+    ///
+    /// This costs a third copy of `T`, but no extra cloning: during a commit
+    /// the old read buffer is moved into the previous state, and the state
+    /// it replaces is reused as the new read buffer. The exception is
+    /// swapping commits (`update_swap` and `SwapCommit`), which leave the old
+    /// read buffer in the write buffer and so have to clone it into the
+    /// previous state.
+    ///
+    /// ```rust
+    /// # use dubble::DoubleBuffered;
+    /// let mut pos = DoubleBuffered::new(0.0).with_previous();
+    ///
+    /// *pos = 1.0;
+    /// pos.update();
+    /// assert!(pos.previous() == Some(&0.0));
+    /// assert!(*pos == 1.0);
+    /// ```
+    pub fn with_previous(mut self) -> Self
+    {
+        if self.prev.is_none()
+        {
+            self.prev = Some(Previous
+            {
+                value: Box::new(self.rbuf.clone()),
+                clone_from: T::clone_from,
+                skipped: false,
+            });
+        }
+
+        self
+    }
+
+    /// Throws away any pending writes, by resetting the write buffer to the
+    /// contents of the read buffer. You could think of this like "revert to
+    /// last save" in a word processor.
+    ///
+    /// This uses `Clone::clone_from`, so the write buffer's allocations are
+    /// reused where the type allows it.
+    ///
+    /// ```rust
+    /// # use dubble::DoubleBuffered;
+    /// let mut my_buf = DoubleBuffered::new(vec![1, 2, 3]);
+    /// my_buf.push(4);
+    /// my_buf.revert();
+    /// assert!(*my_buf.write() == [1, 2, 3]);
+    /// ```
+    #[doc(alias = "discard")]
+    pub fn revert(&mut self)
+    {
+        self.wbuf.clone_from(&self.rbuf);
+        self.dirty = false;
+    }
+}
+
+impl<T: Clone, S: CommitStrategy<T>> DoubleBuffered<T, S>
+{
+    /// Commits the write buffer only if `validate` accepts it. Otherwise the
+    /// pending writes are thrown away, as with `revert`, and the error is
+    /// returned. Either way, the read and write buffers agree afterwards
+    /// (unless the commit strategy says otherwise).
+    ///
+    /// ```rust
+    /// # use dubble::DoubleBuffered;
+    /// let mut hp = DoubleBuffered::new(10);
+    ///
+    /// *hp -= 15;
+    /// let result = hp.try_update(|&hp| if hp >= 0 { Ok(()) } else { Err("dead") });
+    ///
+    /// assert!(result == Err("dead"));
+    /// assert!(*hp == 10);
+    /// assert!(*hp.write() == 10);
+    /// ```
+    pub fn try_update<E, F: FnOnce(&T) -> Result<(), E>>(&mut self, validate: F) -> Result<(), E>
+    {
+        match validate(&self.wbuf)
+        {
+            Ok(()) =>
+            {
+                self.update();
+                Ok(())
+            }
+
+            Err(e) =>
+            {
+                self.revert();
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<T, S> Deref for DoubleBuffered<T, S>
 {
     type Target = T;
 
@@ -206,7 +729,7 @@ impl<T: Clone> Deref for DoubleBuffered<T>
     }
 }
 
-impl<T: Clone> DerefMut for DoubleBuffered<T>
+impl<T, S> DerefMut for DoubleBuffered<T, S>
 {
     fn deref_mut(&mut self) -> &mut T
     {
@@ -214,16 +737,16 @@ impl<T: Clone> DerefMut for DoubleBuffered<T>
     }
 }
 
-impl<T: Default + Clone> Default for DoubleBuffered<T>
+impl<T: Default> Default for DoubleBuffered<T>
 {
     /// Use the default constructor for the type.
     fn default() -> Self
     {
-        Self::construct_with(T::default)
+        Self::from_buffers(T::default(), T::default())
     }
 }
 
-impl<I, T: Index<I> + Clone> Index<I> for DoubleBuffered<T>
+impl<I, T: Index<I>, S> Index<I> for DoubleBuffered<T, S>
 {
     type Output = <T as Index<I>>::Output;
 
@@ -233,15 +756,16 @@ impl<I, T: Index<I> + Clone> Index<I> for DoubleBuffered<T>
     }
 }
 
-impl<I, T: IndexMut<I> + Clone> IndexMut<I> for DoubleBuffered<T>
+impl<I, T: IndexMut<I>, S> IndexMut<I> for DoubleBuffered<T, S>
 {
     fn index_mut(&mut self, index: I) -> &mut Self::Output
     {
-        &mut self.wbuf[index]
+        &mut self.write()[index]
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::cmp_owned)]
 mod tests
 {
     use super::*;
@@ -275,6 +799,15 @@ mod tests
         assert!(*db.read() == String::from("hello, world"));
     }
 
+    #[test]
+    fn default_infers_strategy()
+    {
+        // the commit strategy isn't left for the caller to spell out
+        let db = DoubleBuffered::default();
+        let value: i32 = *db;
+        assert!(value == 0);
+    }
+
     #[test]
     fn basic_int_using_deref()
     {
@@ -314,5 +847,77 @@ mod tests
         db.update();
         assert!(db[0] == 1);
     }
+
+    #[test]
+    fn swap_update()
+    {
+        let mut db = DoubleBuffered::new(vec![1, 2, 3]);
+
+        for (i, x) in db.write().iter_mut().enumerate()
+        {
+            *x = i as i32 * 10;
+        }
+
+        // read view should not change
+        assert!(*db == [1, 2, 3]);
+
+        // buffers trade places
+        db.update_swap();
+        assert!(*db == [0, 10, 20]);
+        assert!(*db.write() == [1, 2, 3]);
+
+        // re-seeding the write buffer from the committed value
+        db[1] = 11;
+        db.update_swap_with(|r, w| w.clone_from(r));
+        assert!(*db == [1, 11, 3]);
+        assert!(*db.write() == [1, 11, 3]);
+    }
+
+    #[test]
+    fn pending_changes_and_generation()
+    {
+        let mut db = DoubleBuffered::<Vec<i32>>::default();
+        assert!(db.generation() == 0);
+
+        // reading doesn't count as a change
+        assert!(db.is_empty());
+        assert!(!db.update_if_changed());
+        assert!(db.generation() == 0);
+
+        db.push(1);
+        assert!(db.has_pending_changes());
+        assert!(db.update_if_changed());
+        assert!(db.generation() == 1);
+        assert!(!db.has_pending_changes());
+
+        db[0] = 2;
+        assert!(db.has_pending_changes());
+        db.revert();
+        assert!(!db.has_pending_changes());
+
+        // plain update always commits
+        db.update();
+        db.update_swap();
+        assert!(db.generation() == 3);
+    }
+
+    #[test]
+    fn revert_and_try_update()
+    {
+        let mut db = DoubleBuffered::new(String::from("ok"));
+
+        db.push_str(" then not ok");
+        db.revert();
+        assert!(*db.write() == "ok");
+
+        db.push('!');
+        assert!(db.try_update(|s| if s.len() < 5 { Ok(()) } else { Err(s.len()) }) == Ok(()));
+        assert!(*db == "ok!");
+
+        db.push_str("!!!");
+        assert!(db.try_update(|s| if s.len() < 5 { Ok(()) } else { Err(s.len()) }) == Err(6));
+        assert!(*db == "ok!");
+        assert!(*db.write() == "ok!");
+    }
 }
 
@@ -0,0 +1,220 @@
+//! A buffer which keeps a history of committed states.
+
+use std::ops::
+{
+    Deref,
+    DerefMut,
+    Index,
+    IndexMut
+};
+
+/// Like `DoubleBuffered`, but keeps the last `N` committed states rather than
+/// just the latest one. A `MultiBuffered<T, 1>` behaves exactly like a
+/// `DoubleBuffered<T>`.
+///
+/// The committed states are kept in a ring; `update` overwrites the oldest one
+/// with the contents of the write buffer, using `Clone::clone_from` so that
+/// allocations are reused. `read_back(k)` returns the state from `k` commits
+/// ago.
+///
+/// ```rust
+/// use dubble::MultiBuffered;
+///
+/// // current and previous positions, for a verlet integrator
+/// let mut pos = MultiBuffered::<f32, 2>::new(0.0);
+///
+/// *pos.write() = 1.0;
+/// pos.update();
+/// *pos.write() = 3.0;
+/// pos.update();
+///
+/// assert!(*pos.read() == 3.0);
+/// assert!(*pos.read_back(1) == 1.0);
+///
+/// // x' = 2x - x_prev
+/// let next = 2.0 * *pos.read() - *pos.read_back(1);
+/// assert!(next == 5.0);
+/// ```
+pub struct MultiBuffered<T: Clone, const N: usize>
+{
+    ring: [T; N],
+
+    /// Index of the most recently committed state in `ring`.
+    head: usize,
+    wbuf: T,
+}
+
+impl<T: Clone, const N: usize> MultiBuffered<T, N>
+{
+    /// Initialises every buffer with the value.
+    ///
+    /// # Panics
+    ///
+    /// If `N` is zero.
+    pub fn new(value: T) -> Self
+    {
+        Self::construct_with(|| value.clone())
+    }
+
+    /// Uses `constructor` to construct each buffer.
+    ///
+    /// # Panics
+    ///
+    /// If `N` is zero.
+    pub fn construct_with<F: Fn() -> T>(constructor: F) -> Self
+    {
+        assert!(N > 0, "MultiBuffered needs to keep at least one state");
+
+        Self
+        {
+            ring: ::std::array::from_fn(|_| constructor()),
+            head: 0,
+            wbuf: constructor(),
+        }
+    }
+
+    /// Returns an immutable reference to the most recently committed state.
+    pub fn read(&self) -> &T
+    {
+        &self.ring[self.head]
+    }
+
+    /// Returns the state committed `k` updates ago, so that `read_back(0)` is
+    /// the same as `read()`.
+    ///
+    /// # Panics
+    ///
+    /// If `k` is not less than `N`.
+    pub fn read_back(&self, k: usize) -> &T
+    {
+        assert!(k < N, "only {} states are kept, but asked for {} back", N, k);
+        &self.ring[(self.head + N - k) % N]
+    }
+
+    /// Returns an iterator over the committed states, from newest to oldest.
+    pub fn history(&self) -> impl Iterator<Item = &T>
+    {
+        (0..N).map(move |k| self.read_back(k))
+    }
+
+    /// Returns a mutable reference to the write buffer.
+    pub fn write(&mut self) -> &mut T
+    {
+        &mut self.wbuf
+    }
+
+    /// Commits the write buffer, making it the newest state and dropping the
+    /// oldest one.
+    pub fn update(&mut self)
+    {
+        self.head = (self.head + 1) % N;
+        self.ring[self.head].clone_from(&self.wbuf);
+    }
+
+    /// Writes the value to the write buffer, and then immediately commits it.
+    pub fn upsert(&mut self, value: T)
+    {
+        *self.write() = value;
+        self.update();
+    }
+
+    /// Returns the write buffer.
+    pub fn unbuffer_write(self) -> T
+    {
+        self.wbuf
+    }
+}
+
+impl<T: Clone, const N: usize> Deref for MultiBuffered<T, N>
+{
+    type Target = T;
+
+    fn deref(&self) -> &T
+    {
+        self.read()
+    }
+}
+
+impl<T: Clone, const N: usize> DerefMut for MultiBuffered<T, N>
+{
+    fn deref_mut(&mut self) -> &mut T
+    {
+        self.write()
+    }
+}
+
+impl<T: Default + Clone, const N: usize> Default for MultiBuffered<T, N>
+{
+    /// Use the default constructor for the type.
+    fn default() -> Self
+    {
+        Self::construct_with(T::default)
+    }
+}
+
+impl<I, T: Index<I> + Clone, const N: usize> Index<I> for MultiBuffered<T, N>
+{
+    type Output = <T as Index<I>>::Output;
+
+    fn index(&self, index: I) -> &Self::Output
+    {
+        &self.read()[index]
+    }
+}
+
+impl<I, T: IndexMut<I> + Clone, const N: usize> IndexMut<I> for MultiBuffered<T, N>
+{
+    fn index_mut(&mut self, index: I) -> &mut Self::Output
+    {
+        &mut self.wbuf[index]
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn history()
+    {
+        let mut mb = MultiBuffered::<i32, 3>::default();
+
+        for i in 1..=4
+        {
+            *mb = i;
+            mb.update();
+        }
+
+        assert!(*mb == 4);
+        assert!(mb.history().cloned().collect::<Vec<_>>() == [4, 3, 2]);
+    }
+
+    #[test]
+    fn one_is_double_buffered()
+    {
+        let mut mb = MultiBuffered::<Vec<i32>, 1>::default();
+
+        mb.write().push(1);
+        assert!(mb.is_empty());
+
+        mb.update();
+        assert!(mb[0] == 1);
+        assert!(*mb.read_back(0) == [1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn no_states()
+    {
+        MultiBuffered::<i32, 0>::new(0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn too_far_back()
+    {
+        let mb = MultiBuffered::<i32, 2>::default();
+        mb.read_back(2);
+    }
+}
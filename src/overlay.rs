@@ -0,0 +1,214 @@
+//! A double-buffer which only stores what was written.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::ops::
+{
+    Deref,
+    Index,
+    IndexMut
+};
+
+/// A double-buffered `Vec` where, instead of a second full copy, the write
+/// side only holds the elements which have been written to since the last
+/// commit, laid over the read side.
+///
+/// Writing to an element copies it into the overlay first, so the memory used
+/// by the write side is proportional to the number of elements edited rather
+/// than to the length of the `Vec`. `update` moves the edited elements into
+/// the read side without cloning them.
+///
+/// ```rust
+/// use dubble::OverlayBuffered;
+///
+/// let mut world = OverlayBuffered::new(vec![0u8; 1 << 20]);
+///
+/// world[10] = 1;
+/// world[20] += 2;
+/// assert!(world[10] == 0);
+/// assert!(world.pending_len() == 2);
+///
+/// // only the two edited tiles are moved across
+/// world.update();
+/// assert!(world[10] == 1 && world[20] == 2);
+/// assert!(world.pending_len() == 0);
+/// ```
+pub struct OverlayBuffered<T: Clone>
+{
+    base: Vec<T>,
+    overlay: HashMap<usize, T>,
+}
+
+impl<T: Clone> OverlayBuffered<T>
+{
+    /// Uses `value` as the read side, with nothing written yet.
+    pub fn new(value: Vec<T>) -> Self
+    {
+        Self
+        {
+            base: value,
+            overlay: HashMap::new(),
+        }
+    }
+
+    /// Returns an immutable reference to the read side.
+    pub fn read(&self) -> &[T]
+    {
+        &self.base
+    }
+
+    /// Returns the write side's version of the element at `index`: the
+    /// pending value if it has been written to, or else the committed one.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn pending(&self, index: usize) -> &T
+    {
+        self.overlay.get(&index).unwrap_or(&self.base[index])
+    }
+
+    /// Returns a mutable reference to the element at `index` on the write
+    /// side, copying it into the overlay if it hasn't been written to since
+    /// the last commit.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn write(&mut self, index: usize) -> &mut T
+    {
+        match self.overlay.entry(index)
+        {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e)   => e.insert(self.base[index].clone()),
+        }
+    }
+
+    /// Returns whether anything has been written since the last commit.
+    pub fn has_pending_changes(&self) -> bool
+    {
+        !self.overlay.is_empty()
+    }
+
+    /// Returns the number of elements written since the last commit.
+    pub fn pending_len(&self) -> usize
+    {
+        self.overlay.len()
+    }
+
+    /// Iterates over the elements written since the last commit, with their
+    /// indices, in no particular order.
+    pub fn pending_iter(&self) -> impl Iterator<Item = (usize, &T)>
+    {
+        self.overlay.iter().map(|(&i, x)| (i, x))
+    }
+
+    /// Moves the written elements into the read side.
+    pub fn update(&mut self)
+    {
+        for (index, value) in self.overlay.drain()
+        {
+            self.base[index] = value;
+        }
+    }
+
+    /// Throws away everything written since the last commit.
+    #[doc(alias = "discard")]
+    pub fn revert(&mut self)
+    {
+        self.overlay.clear();
+    }
+
+    /// Returns the read side, discarding the write side.
+    pub fn unbuffer_read(self) -> Vec<T>
+    {
+        self.base
+    }
+
+    /// Returns the write side, discarding the read side.
+    pub fn unbuffer_write(mut self) -> Vec<T>
+    {
+        self.update();
+        self.base
+    }
+}
+
+impl<T: Clone> Default for OverlayBuffered<T>
+{
+    fn default() -> Self
+    {
+        Self::new(Vec::new())
+    }
+}
+
+impl<T: Clone> From<Vec<T>> for OverlayBuffered<T>
+{
+    fn from(value: Vec<T>) -> Self
+    {
+        Self::new(value)
+    }
+}
+
+impl<T: Clone> Deref for OverlayBuffered<T>
+{
+    type Target = [T];
+
+    fn deref(&self) -> &[T]
+    {
+        self.read()
+    }
+}
+
+impl<T: Clone> Index<usize> for OverlayBuffered<T>
+{
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T
+    {
+        &self.base[index]
+    }
+}
+
+impl<T: Clone> IndexMut<usize> for OverlayBuffered<T>
+{
+    fn index_mut(&mut self, index: usize) -> &mut T
+    {
+        self.write(index)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn writes_stay_in_the_overlay()
+    {
+        let mut db = OverlayBuffered::new(vec![String::from("a"), "b".into(), "c".into()]);
+
+        db[1].push('!');
+        db[1].push('?');
+        assert!(db[1] == "b");
+        assert!(db.pending(1) == "b!?");
+        assert!(db.pending(2) == "c");
+        assert!(db.pending_iter().map(|(i, _)| i).collect::<Vec<_>>() == [1]);
+
+        db.update();
+        assert!(*db == ["a", "b!?", "c"]);
+        assert!(!db.has_pending_changes());
+    }
+
+    #[test]
+    fn revert_and_unbuffer()
+    {
+        let mut db = OverlayBuffered::new(vec![1, 2, 3]);
+        db[0] = 10;
+        db.revert();
+        db.update();
+        assert!(*db == [1, 2, 3]);
+
+        db[2] = 30;
+        assert!(db.unbuffer_write() == [1, 2, 30]);
+    }
+}
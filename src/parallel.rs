@@ -0,0 +1,345 @@
+//! Stepping and committing a slice-like buffer on several threads.
+//!
+//! Every thread reads the whole read buffer, and writes to its own disjoint
+//! chunk of the write buffer, so no locking is needed. By default the chunks
+//! are handed to scoped threads; with the `rayon` feature enabled they're run
+//! on rayon's thread pool instead.
+//!
+//! ```rust
+//! use dubble::DoubleBuffered;
+//!
+//! let mut cells = DoubleBuffered::new(vec![0u32; 10_000]);
+//! cells[5_000] = 9;
+//! cells.update();
+//!
+//! // a 1D blur, where each cell only depends on the old state
+//! cells.par_step(|i, old, new|
+//! {
+//!     let left = if i > 0 { old[i - 1] } else { 0 };
+//!     let right = old.get(i + 1).cloned().unwrap_or(0);
+//!     *new = (left + old[i] + right) / 3;
+//! });
+//!
+//! cells.update();
+//! assert!(cells[4_999..5_002] == [3, 3, 3]);
+//! ```
+//!
+//! For large buffers, the commit itself can be split up the same way with
+//! `par_update`.
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::cmp;
+use std::mem;
+use std::sync::OnceLock;
+use std::thread;
+
+use DoubleBuffered;
+use strategy::CommitStrategy;
+
+/// The least number of bytes `par_update` gives each thread to copy. Below
+/// this, starting the thread costs more than the copy.
+const MIN_COMMIT_CHUNK_BYTES: usize = 256 * 1024;
+
+impl<T, S> DoubleBuffered<T, S>
+{
+    /// Calls `f` for every element of the write buffer, spread across as many
+    /// threads as there are cores. `f` is given the index of the element, the
+    /// whole read buffer, and the element of the write buffer to fill in.
+    ///
+    /// This marks the buffer as having pending changes, like `write`.
+    pub fn par_step<E, F>(&mut self, f: F)
+        where T: AsRef<[E]> + AsMut<[E]>,
+              E: Send + Sync,
+              F: Fn(usize, &[E], &mut E) + Send + Sync
+    {
+        self.dirty = true;
+        let read = self.rbuf.as_ref();
+        let write = self.wbuf.as_mut();
+
+        #[cfg(feature = "rayon")]
+        write.par_iter_mut().enumerate().for_each(|(i, new)| f(i, read, new));
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            let chunk_len = write.len().div_ceil(thread_count()).max(1);
+            for_each_chunk(read, write, chunk_len, |start, read, chunk|
+            {
+                for (i, new) in chunk.iter_mut().enumerate()
+                {
+                    f(start + i, read, new);
+                }
+            });
+        }
+    }
+
+    /// Splits the write buffer into chunks of `chunk_len` elements (the last
+    /// one may be shorter), and calls `f` for each of them on another thread.
+    /// `f` is given the index of the first element of the chunk, the whole
+    /// read buffer, and the chunk of the write buffer.
+    ///
+    /// Without the `rayon` feature, the chunks are shared out between as many
+    /// threads as there are cores.
+    ///
+    /// This marks the buffer as having pending changes, like `write`.
+    ///
+    /// # Panics
+    ///
+    /// If `chunk_len` is zero.
+    ///
+    /// ```rust
+    /// # use dubble::DoubleBuffered;
+    /// let mut rows = DoubleBuffered::new(vec![1; 4 * 4]);
+    ///
+    /// // one row per chunk
+    /// rows.par_step_chunks(4, |start, old, row|
+    /// {
+    ///     let sum: i32 = old[start..start + 4].iter().sum();
+    ///     row.iter_mut().for_each(|x| *x = sum);
+    /// });
+    ///
+    /// rows.update();
+    /// assert!(rows.iter().all(|&x| x == 4));
+    /// ```
+    pub fn par_step_chunks<E, F>(&mut self, chunk_len: usize, f: F)
+        where T: AsRef<[E]> + AsMut<[E]>,
+              E: Send + Sync,
+              F: Fn(usize, &[E], &mut [E]) + Send + Sync
+    {
+        assert!(chunk_len != 0, "chunk_len must be greater than zero");
+
+        self.dirty = true;
+        let read = self.rbuf.as_ref();
+        let write = self.wbuf.as_mut();
+
+        #[cfg(feature = "rayon")]
+        write.par_chunks_mut(chunk_len)
+            .enumerate()
+            .for_each(|(i, chunk)| f(i * chunk_len, read, chunk));
+
+        #[cfg(not(feature = "rayon"))]
+        for_each_chunk(read, write, chunk_len, f);
+    }
+}
+
+impl<T, S: CommitStrategy<T>> DoubleBuffered<T, S>
+{
+    /// Commits the write buffer like `update`, but for slice-like buffers,
+    /// copies it in chunks on several threads at once. Whatever the commit
+    /// strategy, the elements are cloned across, so afterwards both buffers
+    /// hold the same contents.
+    ///
+    /// Buffers which are too small to be worth splitting up are copied on
+    /// the current thread. If the buffer being copied into has a different
+    /// length to the write buffer, this falls back to `update`, since a slice
+    /// can't be resized. That's the read buffer, or the previous state if the
+    /// buffer was created `with_previous`, since that's what is reused.
+    ///
+    /// Like `CloneFromCommit`, this reuses the read buffer's allocation.
+    /// Whether the threads make it any faster than that depends on the
+    /// number of cores and on memory bandwidth; run
+    /// `cargo run --release --example parallel_commit` to compare them on
+    /// your machine. The README has single-core measurements, where it only
+    /// keeps up with `CloneFromCommit`; it hasn't been measured on more cores.
+    ///
+    /// ```rust
+    /// # use dubble::DoubleBuffered;
+    /// let mut particles = DoubleBuffered::new(vec![0.0f32; 4 << 20]);
+    /// particles[123] = 1.0;
+    ///
+    /// particles.par_update();
+    /// assert!(particles[123] == 1.0);
+    /// ```
+    pub fn par_update<E>(&mut self)
+        where T: AsRef<[E]> + AsMut<[E]>,
+              E: Clone + Send + Sync
+    {
+        // with the previous state kept, that's what gets rotated in and
+        // copied into, rather than the read buffer
+        let into = self.prev.as_ref().map_or(&self.rbuf, |prev| &*prev.value);
+        if into.as_ref().len() != self.wbuf.as_ref().len()
+        {
+            self.update();
+            return;
+        }
+
+        self.rotate_previous();
+        copy_chunks(self.rbuf.as_mut(), self.wbuf.as_ref());
+        self.committed();
+    }
+}
+
+/// Clones `src` into `dst`, which must have the same length, splitting the
+/// work between threads if there's enough of it.
+fn copy_chunks<E: Clone + Send + Sync>(dst: &mut [E], src: &[E])
+{
+    let min_chunk_len = cmp::max(MIN_COMMIT_CHUNK_BYTES / cmp::max(mem::size_of::<E>(), 1), 1);
+    let chunk_len = cmp::max(src.len().div_ceil(thread_count()), min_chunk_len);
+
+    #[cfg(feature = "rayon")]
+    dst.par_chunks_mut(chunk_len)
+        .zip(src.par_chunks(chunk_len))
+        .for_each(|(d, s)| d.clone_from_slice(s));
+
+    #[cfg(not(feature = "rayon"))]
+    {
+        if chunk_len >= src.len()
+        {
+            dst.clone_from_slice(src);
+            return;
+        }
+
+        thread::scope(|scope|
+        {
+            for (d, s) in dst.chunks_mut(chunk_len).zip(src.chunks(chunk_len))
+            {
+                scope.spawn(move || d.clone_from_slice(s));
+            }
+        });
+    }
+}
+
+fn thread_count() -> usize
+{
+    // finding this out can mean reading cgroup files, which is slow enough to
+    // matter once per frame
+    static THREADS: OnceLock<usize> = OnceLock::new();
+    *THREADS.get_or_init(|| thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+}
+
+/// Shares the chunks of `write` out between scoped threads, and calls `f` on
+/// each of them.
+#[cfg(not(feature = "rayon"))]
+fn for_each_chunk<E, F>(read: &[E], write: &mut [E], chunk_len: usize, f: F)
+    where E: Send + Sync,
+          F: Fn(usize, &[E], &mut [E]) + Sync
+{
+    let chunks: Vec<_> = write.chunks_mut(chunk_len).enumerate().collect();
+    let threads = thread_count().min(chunks.len());
+
+    if threads <= 1
+    {
+        for (i, chunk) in chunks
+        {
+            f(i * chunk_len, read, chunk);
+        }
+
+        return;
+    }
+
+    let mut work: Vec<Vec<_>> = (0..threads).map(|_| Vec::new()).collect();
+    for (i, chunk) in chunks
+    {
+        work[i % threads].push((i, chunk));
+    }
+
+    let f = &f;
+    thread::scope(|scope|
+    {
+        for chunks in work
+        {
+            scope.spawn(move ||
+            {
+                for (i, chunk) in chunks
+                {
+                    f(i * chunk_len, read, chunk);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests
+{
+    use DoubleBuffered;
+    use strategy::SwapCommit;
+
+    fn blur(i: usize, old: &[u64], new: &mut u64)
+    {
+        let left = if i > 0 { old[i - 1] } else { 0 };
+        let right = old.get(i + 1).cloned().unwrap_or(0);
+        *new = left + old[i] + right;
+    }
+
+    #[test]
+    fn matches_serial_step()
+    {
+        let start: Vec<u64> = (0..100_003).map(|i| i * 7 % 13).collect();
+        let mut serial = DoubleBuffered::new(start.clone());
+        let mut parallel = DoubleBuffered::new(start);
+
+        for _ in 0..3
+        {
+            serial.step_elements().enumerate().for_each(|(i, (_, old, new))| blur(i, old, new));
+            parallel.par_step(blur);
+
+            serial.update();
+            parallel.update();
+        }
+
+        assert!(*serial == *parallel);
+    }
+
+    #[test]
+    fn chunks_cover_the_buffer()
+    {
+        let mut db = DoubleBuffered::new(vec![0usize; 1_001]);
+        db.par_step_chunks(10, |start, _, chunk|
+        {
+            assert!(chunk.len() == 10 || start == 1_000);
+            for (i, x) in chunk.iter_mut().enumerate()
+            {
+                *x = start + i;
+            }
+        });
+
+        db.update();
+        assert!(db.iter().enumerate().all(|(i, &x)| i == x));
+    }
+
+    #[test]
+    fn par_update()
+    {
+        // big enough to be split up, and not a multiple of the chunk size
+        let len = (1 << 20) + 3;
+        let mut db = DoubleBuffered::new(vec![0u8; len])
+            .with_strategy::<SwapCommit>()
+            .with_previous();
+
+        db[0] = 1;
+        db[len - 1] = 2;
+        db.par_update();
+        assert!(!db.has_pending_changes());
+        assert!(db[0] == 1 && db[len - 1] == 2);
+        assert!(db.previous().unwrap()[0] == 0);
+
+        // cloned rather than swapped, despite the strategy
+        assert!(db.write()[len - 1] == 2);
+
+        // lengths differ, so it's the strategy's job
+        db.push(3);
+        db.par_update();
+        assert!(db.len() == len + 1);
+        assert!(db.previous().unwrap().len() == len);
+        assert!(db.generation() == 2);
+    }
+
+    #[test]
+    fn par_update_after_resize()
+    {
+        let mut db = DoubleBuffered::new(vec![0u8; 4]).with_previous();
+        db.push(1);
+        db.update();
+
+        // the read buffer has the new length, but the previous state doesn't
+        db.par_update();
+        assert!(*db == [0, 0, 0, 0, 1]);
+        assert!(db.previous().unwrap() == &[0, 0, 0, 0, 1]);
+
+        db[0] = 2;
+        db.par_update();
+        assert!(*db == [2, 0, 0, 0, 1]);
+        assert!(db.previous().unwrap() == &[0, 0, 0, 0, 1]);
+    }
+}
@@ -0,0 +1,314 @@
+//! A lock-free double-buffer for small `Copy` types.
+//!
+//! `SeqDoubleBuffered` is written to by its owner in the usual way, with
+//! `write` and `update`. Any number of other threads can read the committed
+//! value through a `ReadHandle`, without a mutex. The committed value is
+//! guarded by a sequence lock: `update` bumps a counter before and after
+//! copying the value in, and readers copy the value out and retry if the
+//! counter tells them a write happened in the meantime.
+//!
+//! This works best for small values that change often, like transforms,
+//! input state or counters. Readers never block the writer; the writer only
+//! delays readers for as long as it takes to copy one `T`.
+//!
+//! The value is copied in and out a word at a time through atomics, so a
+//! reader racing with the writer is well-defined. That doesn't extend to
+//! padding bytes, which can't be copied atomically, so `T` should have none:
+//! numbers, and arrays and tuples of the same type of number, are fine.
+//!
+//! ```rust
+//! use std::thread;
+//! use dubble::SeqDoubleBuffered;
+//!
+//! let mut input = SeqDoubleBuffered::new((0.0f32, 0.0f32));
+//! let reader = input.reader();
+//!
+//! let handle = thread::spawn(move ||
+//! {
+//!     let (x, y) = reader.read();
+//!     assert!(x == y);
+//! });
+//!
+//! *input.write() = (1.5, 1.5);
+//! input.update();
+//!
+//! handle.join().unwrap();
+//! ```
+
+use std::cmp;
+use std::hint;
+use std::marker::PhantomData;
+use std::mem::
+{
+    self,
+    MaybeUninit
+};
+use std::ops::
+{
+    Deref,
+    DerefMut
+};
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::
+{
+    self,
+    AtomicUsize,
+    Ordering
+};
+
+const WORD: usize = mem::size_of::<usize>();
+
+struct Shared<T: Copy>
+{
+    /// Odd while the writer is copying into `words`.
+    seq: AtomicUsize,
+
+    /// The bytes of the committed value. They're copied in and out a word at
+    /// a time with atomics, since a reader can overlap with the writer, and
+    /// plain (or volatile) accesses racing like that would be undefined
+    /// behaviour.
+    words: Box<[AtomicUsize]>,
+    value: PhantomData<T>,
+}
+
+// Readers only ever copy the value out, and discard the copy unless `seq`
+// shows that no write overlapped with it.
+unsafe impl<T: Copy + Send> Sync for Shared<T> {}
+
+impl<T: Copy> Shared<T>
+{
+    fn new(value: T) -> Self
+    {
+        let words = (0..mem::size_of::<T>().div_ceil(WORD)).map(|_| AtomicUsize::new(0)).collect();
+        let shared = Shared { seq: AtomicUsize::new(0), words, value: PhantomData };
+        shared.store(value);
+        shared
+    }
+
+    fn load(&self) -> T
+    {
+        loop
+        {
+            let before = self.seq.load(Ordering::Acquire);
+            if before & 1 == 1
+            {
+                hint::spin_loop();
+                continue;
+            }
+
+            // this may race with the writer, so it's only a valid `T` once
+            // the sequence number shows that it didn't
+            let mut value = MaybeUninit::<T>::uninit();
+            let dst = value.as_mut_ptr() as *mut u8;
+            for (i, word) in self.words.iter().enumerate()
+            {
+                let bytes = word.load(Ordering::Relaxed).to_ne_bytes();
+                let start = i * WORD;
+                let len = cmp::min(WORD, mem::size_of::<T>() - start);
+                unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), dst.add(start), len) };
+            }
+
+            atomic::fence(Ordering::Acquire);
+            if self.seq.load(Ordering::Relaxed) == before
+            {
+                return unsafe { value.assume_init() };
+            }
+        }
+    }
+
+    /// Must only be called by the single writer.
+    fn store(&self, value: T)
+    {
+        let seq = self.seq.load(Ordering::Relaxed);
+        self.seq.store(seq.wrapping_add(1), Ordering::Relaxed);
+        atomic::fence(Ordering::Release);
+
+        let src = &value as *const T as *const u8;
+        for (i, word) in self.words.iter().enumerate()
+        {
+            let mut bytes = [0u8; WORD];
+            let start = i * WORD;
+            let len = cmp::min(WORD, mem::size_of::<T>() - start);
+
+            // if `T` has padding, this copies uninitialised bytes into an
+            // integer, which Rust doesn't allow either; there's no sound way
+            // to copy them atomically yet, so prefer types without padding
+            unsafe { ptr::copy_nonoverlapping(src.add(start), bytes.as_mut_ptr(), len) };
+            word.store(usize::from_ne_bytes(bytes), Ordering::Relaxed);
+        }
+
+        self.seq.store(seq.wrapping_add(2), Ordering::Release);
+    }
+}
+
+/// A double-buffer for `Copy` types whose committed value can be read from
+/// any thread through a `ReadHandle`. See the `seqlock` module for details.
+///
+/// The owner uses it exactly like a `DoubleBuffered`.
+pub struct SeqDoubleBuffered<T: Copy>
+{
+    /// The owner's copy of the committed value, so that it can be read
+    /// without going through the sequence lock.
+    rbuf: T,
+    wbuf: T,
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Copy> SeqDoubleBuffered<T>
+{
+    /// Initialises both buffers with the value.
+    pub fn new(value: T) -> Self
+    {
+        Self
+        {
+            rbuf: value,
+            wbuf: value,
+            shared: Arc::new(Shared::new(value)),
+        }
+    }
+
+    /// Returns an immutable reference to the read buffer.
+    pub fn read(&self) -> &T
+    {
+        &self.rbuf
+    }
+
+    /// Returns a mutable reference to the write buffer. Changes are not
+    /// visible to anyone until `update` is called.
+    pub fn write(&mut self) -> &mut T
+    {
+        &mut self.wbuf
+    }
+
+    /// Copies the write buffer into the read buffer, and publishes it to
+    /// readers on other threads.
+    pub fn update(&mut self)
+    {
+        self.rbuf = self.wbuf;
+        self.shared.store(self.wbuf);
+    }
+
+    /// Writes the value to the write buffer, and then immediately updates the
+    /// read buffer.
+    pub fn upsert(&mut self, value: T)
+    {
+        *self.write() = value;
+        self.update();
+    }
+
+    /// Creates a handle through which other threads can read the committed
+    /// value.
+    pub fn reader(&self) -> ReadHandle<T>
+    {
+        ReadHandle { shared: self.shared.clone() }
+    }
+}
+
+impl<T: Copy> Deref for SeqDoubleBuffered<T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &T
+    {
+        self.read()
+    }
+}
+
+impl<T: Copy> DerefMut for SeqDoubleBuffered<T>
+{
+    fn deref_mut(&mut self) -> &mut T
+    {
+        self.write()
+    }
+}
+
+impl<T: Copy + Default> Default for SeqDoubleBuffered<T>
+{
+    fn default() -> Self
+    {
+        Self::new(T::default())
+    }
+}
+
+/// Reads the committed value of a `SeqDoubleBuffered` from another thread.
+/// These can be cloned and sent between threads freely.
+pub struct ReadHandle<T: Copy>
+{
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Copy> ReadHandle<T>
+{
+    /// Returns a copy of the committed value. If the writer is in the middle
+    /// of an update, this spins until it is done.
+    pub fn read(&self) -> T
+    {
+        self.shared.load()
+    }
+}
+
+impl<T: Copy> Clone for ReadHandle<T>
+{
+    fn clone(&self) -> Self
+    {
+        ReadHandle { shared: self.shared.clone() }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn basic()
+    {
+        let mut sb = SeqDoubleBuffered::new(0);
+        let r = sb.reader();
+
+        *sb = 3;
+        assert!(*sb == 0);
+        assert!(r.read() == 0);
+
+        sb.update();
+        assert!(*sb == 3);
+        assert!(r.read() == 3);
+    }
+
+    #[test]
+    fn no_torn_reads()
+    {
+        const ITERS: u64 = 100_000;
+
+        let mut sb = SeqDoubleBuffered::new([0u64; 16]);
+
+        let readers: Vec<_> = (0..4).map(|_|
+        {
+            let r = sb.reader();
+            thread::spawn(move ||
+            {
+                let mut last = 0;
+                while last != ITERS
+                {
+                    let value = r.read();
+                    assert!(value.iter().all(|&x| x == value[0]), "torn read: {:?}", value);
+                    assert!(value[0] >= last);
+                    last = value[0];
+                }
+            })
+        }).collect();
+
+        for i in 1..=ITERS
+        {
+            *sb.write() = [i; 16];
+            sb.update();
+        }
+
+        for reader in readers
+        {
+            reader.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! `serde` support, enabled with the `serde` feature.
+//!
+//! By default, a `DoubleBuffered` is serialized with both of its buffers, as a
+//! struct with `read` and `write` fields, so that a buffer saved in the middle
+//! of a frame comes back exactly as it was.
+//!
+//! If you only care about the committed state, for example in a save-game,
+//! use `read_only` instead. It saves just the read buffer, and when loading
+//! initialises both buffers with it, as if `update` had just been called.
+//!
+//! Either way, the previous state kept by `with_previous` is not saved, and a
+//! loaded buffer doesn't keep one; call `with_previous` on it again if you
+//! need to.
+//!
+//! ```rust
+//! # extern crate serde;
+//! # extern crate serde_json;
+//! # extern crate dubble;
+//! use serde::{Deserialize, Serialize};
+//! use dubble::DoubleBuffered;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct SaveGame
+//! {
+//!     // only the committed value is saved
+//!     #[serde(with = "dubble::serialize::read_only")]
+//!     score: DoubleBuffered<u32>,
+//!
+//!     // both buffers are saved
+//!     level: DoubleBuffered<u8>,
+//! }
+//!
+//! # fn main() {
+//! let mut save = SaveGame
+//! {
+//!     score: DoubleBuffered::new(10),
+//!     level: DoubleBuffered::new(1),
+//! };
+//!
+//! *save.score = 20;
+//! *save.level = 2;
+//!
+//! let json = serde_json::to_string(&save).unwrap();
+//! assert!(json == r#"{"score":10,"level":{"read":1,"write":2}}"#);
+//!
+//! let mut loaded: SaveGame = serde_json::from_str(&json).unwrap();
+//! assert!(*loaded.score.write() == 10);
+//! assert!(*loaded.level.write() == 2);
+//! # }
+//! ```
+
+use serde::
+{
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer
+};
+
+use DoubleBuffered;
+
+#[derive(Serialize)]
+#[serde(rename = "DoubleBuffered")]
+struct Buffers<'a, T: 'a>
+{
+    read: &'a T,
+    write: &'a T,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "DoubleBuffered")]
+struct OwnedBuffers<T>
+{
+    read: T,
+    write: T,
+}
+
+impl<T: Serialize, S> Serialize for DoubleBuffered<T, S>
+{
+    fn serialize<Z: Serializer>(&self, serializer: Z) -> Result<Z::Ok, Z::Error>
+    {
+        Buffers { read: self.read(), write: &self.wbuf }.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>, S> Deserialize<'de> for DoubleBuffered<T, S>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    {
+        let buffers = OwnedBuffers::deserialize(deserializer)?;
+        let mut buf = DoubleBuffered::from_buffers(buffers.read, buffers.write);
+
+        // there's no telling whether the write buffer differs, so assume it does
+        buf.dirty = true;
+        Ok(buf)
+    }
+}
+
+/// Serializes only the read buffer of a `DoubleBuffered`. Use it with
+/// `#[serde(with = "dubble::serialize::read_only")]`.
+pub mod read_only
+{
+    use serde::
+    {
+        Deserialize,
+        Deserializer,
+        Serialize,
+        Serializer
+    };
+
+    use DoubleBuffered;
+
+    /// Serializes the read buffer.
+    pub fn serialize<T, S, Z>(buf: &DoubleBuffered<T, S>, serializer: Z) -> Result<Z::Ok, Z::Error>
+        where T: Serialize,
+              Z: Serializer
+    {
+        buf.read().serialize(serializer)
+    }
+
+    /// Deserializes a value, and initialises both buffers with it.
+    pub fn deserialize<'de, T, S, D>(deserializer: D) -> Result<DoubleBuffered<T, S>, D::Error>
+        where T: Deserialize<'de> + Clone,
+              D: Deserializer<'de>
+    {
+        let value = T::deserialize(deserializer)?;
+        Ok(DoubleBuffered::from_buffers(value.clone(), value))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use serde_json;
+
+    use DoubleBuffered;
+    use strategy::SwapCommit;
+
+    #[test]
+    fn round_trip_both_buffers()
+    {
+        let mut db = DoubleBuffered::new(vec![1, 2]);
+        db.push(3);
+
+        let json = serde_json::to_string(&db).unwrap();
+        assert!(json == r#"{"read":[1,2],"write":[1,2,3]}"#);
+
+        let mut loaded: DoubleBuffered<Vec<i32>> = serde_json::from_str(&json).unwrap();
+        assert!(*loaded == [1, 2]);
+        loaded.update();
+        assert!(*loaded == [1, 2, 3]);
+    }
+
+    #[test]
+    fn previous_is_not_saved()
+    {
+        let mut db = DoubleBuffered::new(1).with_previous();
+        db.upsert(2);
+
+        let json = serde_json::to_string(&db).unwrap();
+        assert!(json == r#"{"read":2,"write":2}"#);
+
+        let loaded: DoubleBuffered<i32> = serde_json::from_str(&json).unwrap();
+        assert!(loaded.previous().is_none());
+    }
+
+    #[test]
+    fn read_only()
+    {
+        let mut db = DoubleBuffered::new(String::from("saved"))
+            .with_strategy::<SwapCommit>();
+        db.push_str(" but not this");
+
+        let mut json = Vec::new();
+        super::read_only::serialize(&db, &mut serde_json::Serializer::new(&mut json)).unwrap();
+        assert!(json == br#""saved""#);
+
+        let loaded: DoubleBuffered<String, SwapCommit> =
+            super::read_only::deserialize(&mut serde_json::Deserializer::from_slice(&json)).unwrap();
+        assert!(*loaded == "saved");
+        assert!(loaded.unbuffer_write() == "saved");
+    }
+}
@@ -0,0 +1,218 @@
+//! A double-buffer which publishes its committed state as an `Arc`.
+
+use std::ops::
+{
+    Deref,
+    DerefMut,
+    Index,
+    IndexMut
+};
+use std::sync::Arc;
+
+/// Like `DoubleBuffered`, except that the read buffer is kept in an `Arc`.
+///
+/// `snapshot` hands out a clone of that `Arc`, which stays valid (and
+/// unchanged) for as long as you like, no matter how many times the buffer is
+/// written to and updated in the meantime. This is useful for handing a
+/// consistent frame to a background job, such as saving the game, without
+/// cloning it first.
+///
+/// `update` costs the same single clone as `DoubleBuffered::update`. If no
+/// snapshots of the previous state are still alive, its allocation is reused.
+///
+/// ```rust
+/// use dubble::SnapshotBuffered;
+///
+/// let mut world = SnapshotBuffered::new(vec![1, 2, 3]);
+///
+/// let saved = world.snapshot();
+///
+/// world.push(4);
+/// world.update();
+/// assert!(*world == [1, 2, 3, 4]);
+///
+/// // the snapshot still sees the frame it was taken from
+/// assert!(*saved == [1, 2, 3]);
+/// ```
+pub struct SnapshotBuffered<T: Clone>
+{
+    rbuf: Arc<T>,
+    wbuf: T,
+}
+
+impl<T: Clone> SnapshotBuffered<T>
+{
+    /// Initialises both buffers with the value.
+    pub fn new(value: T) -> Self
+    {
+        Self
+        {
+            rbuf: Arc::new(value.clone()),
+            wbuf: value,
+        }
+    }
+
+    /// Uses `constructor` to construct each buffer.
+    pub fn construct_with<F: Fn() -> T>(constructor: F) -> Self
+    {
+        Self
+        {
+            rbuf: Arc::new(constructor()),
+            wbuf: constructor(),
+        }
+    }
+
+    /// Returns an immutable reference to the read buffer.
+    pub fn read(&self) -> &T
+    {
+        &self.rbuf
+    }
+
+    /// Returns a shared handle to the read buffer, which is not affected by
+    /// later updates.
+    pub fn snapshot(&self) -> Arc<T>
+    {
+        self.rbuf.clone()
+    }
+
+    /// Returns a mutable reference to the write buffer.
+    pub fn write(&mut self) -> &mut T
+    {
+        &mut self.wbuf
+    }
+
+    /// Copies the write buffer into the read buffer. Snapshots taken before
+    /// this keep the old state.
+    pub fn update(&mut self)
+    {
+        match Arc::get_mut(&mut self.rbuf)
+        {
+            Some(rbuf) => rbuf.clone_from(&self.wbuf),
+            None       => self.rbuf = Arc::new(self.wbuf.clone()),
+        }
+    }
+
+    /// Commits the write buffer, and returns a snapshot of the new state.
+    pub fn update_snapshot(&mut self) -> Arc<T>
+    {
+        self.update();
+        self.snapshot()
+    }
+
+    /// Writes the value to the write buffer, and then immediately updates the
+    /// read buffer.
+    pub fn upsert(&mut self, value: T)
+    {
+        *self.write() = value;
+        self.update();
+    }
+
+    /// Returns the write buffer.
+    pub fn unbuffer_write(self) -> T
+    {
+        self.wbuf
+    }
+}
+
+impl<T: Clone> Deref for SnapshotBuffered<T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &T
+    {
+        self.read()
+    }
+}
+
+impl<T: Clone> DerefMut for SnapshotBuffered<T>
+{
+    fn deref_mut(&mut self) -> &mut T
+    {
+        self.write()
+    }
+}
+
+impl<T: Default + Clone> Default for SnapshotBuffered<T>
+{
+    /// Use the default constructor for the type.
+    fn default() -> Self
+    {
+        Self::construct_with(T::default)
+    }
+}
+
+impl<I, T: Index<I> + Clone> Index<I> for SnapshotBuffered<T>
+{
+    type Output = <T as Index<I>>::Output;
+
+    fn index(&self, index: I) -> &Self::Output
+    {
+        &self.rbuf[index]
+    }
+}
+
+impl<I, T: IndexMut<I> + Clone> IndexMut<I> for SnapshotBuffered<T>
+{
+    fn index_mut(&mut self, index: I) -> &mut Self::Output
+    {
+        &mut self.wbuf[index]
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn snapshot_outlives_updates()
+    {
+        let mut sb = SnapshotBuffered::new(0);
+        *sb = 1;
+        let one = sb.update_snapshot();
+
+        for i in 2..10
+        {
+            *sb = i;
+            sb.update();
+        }
+
+        assert!(*one == 1);
+        assert!(*sb == 9);
+    }
+
+    #[test]
+    fn reuses_unshared_allocation()
+    {
+        let mut sb = SnapshotBuffered::new(String::with_capacity(64));
+        let ptr = Arc::as_ptr(&sb.rbuf);
+
+        sb.push_str("hello");
+        sb.update();
+        assert!(Arc::as_ptr(&sb.rbuf) == ptr);
+
+        // while a snapshot is alive the old state must be left alone
+        let snap = sb.snapshot();
+        sb.push_str(" world");
+        sb.update();
+        assert!(Arc::as_ptr(&sb.rbuf) != ptr);
+        assert!(*snap == "hello");
+        assert!(*sb == "hello world");
+    }
+
+    #[test]
+    fn snapshot_on_another_thread()
+    {
+        let mut sb = SnapshotBuffered::new(vec![1, 2, 3]);
+        let snap = sb.snapshot();
+
+        let job = thread::spawn(move || snap.iter().sum::<i32>());
+
+        sb.clear();
+        sb.update();
+
+        assert!(job.join().unwrap() == 6);
+        assert!(sb.is_empty());
+    }
+}
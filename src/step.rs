@@ -0,0 +1,145 @@
+//! Reading the old state while writing the new one.
+//!
+//! The usual simulation step reads everyone's old state and writes its own
+//! new state. Through `Deref` and `DerefMut` the two buffers can't be borrowed
+//! at the same time, so `step` hands out both at once instead, and
+//! `step_elements` does the same element by element.
+//!
+//! ```rust
+//! use dubble::DoubleBuffered;
+//!
+//! // each body is pulled towards the centre of all of them
+//! let mut bodies = DoubleBuffered::new(vec![0.0f32, 4.0, 8.0]);
+//!
+//! for (old, all, new) in bodies.step_elements()
+//! {
+//!     let centre = all.iter().sum::<f32>() / all.len() as f32;
+//!     *new = old + (centre - old) / 2.0;
+//! }
+//!
+//! // every body saw the same old state, whatever order they were updated in
+//! bodies.update();
+//! assert!(*bodies == [2.0, 4.0, 6.0]);
+//! ```
+
+use std::iter::Zip;
+use std::slice;
+
+use DoubleBuffered;
+
+impl<T, S> DoubleBuffered<T, S>
+{
+    /// Calls `f` with the read buffer and the write buffer at once. This marks
+    /// the buffer as having pending changes, like `write`.
+    ///
+    /// ```rust
+    /// # use dubble::DoubleBuffered;
+    /// let mut counter = DoubleBuffered::new(1);
+    /// counter.step(|old, new| *new = old * 10);
+    /// counter.step(|old, new| *new += old);
+    /// counter.update();
+    /// assert!(*counter == 11);
+    /// ```
+    pub fn step<R, F: FnOnce(&T, &mut T) -> R>(&mut self, f: F) -> R
+    {
+        self.dirty = true;
+        f(&self.rbuf, &mut self.wbuf)
+    }
+
+    /// Iterates over the elements of a slice-like buffer, yielding for each
+    /// one its value in the read buffer, the whole read buffer, and its value
+    /// in the write buffer. This marks the buffer as having pending changes,
+    /// like `write`.
+    ///
+    /// If the buffers have different lengths, the iterator stops at the end
+    /// of the shorter one.
+    pub fn step_elements<E>(&mut self) -> StepElements<'_, E>
+        where T: AsRef<[E]> + AsMut<[E]>
+    {
+        self.dirty = true;
+
+        let read = self.rbuf.as_ref();
+        StepElements
+        {
+            read,
+            elements: read.iter().zip(self.wbuf.as_mut().iter_mut()),
+        }
+    }
+}
+
+/// Iterator returned by `DoubleBuffered::step_elements`.
+pub struct StepElements<'a, E: 'a>
+{
+    read: &'a [E],
+    elements: Zip<slice::Iter<'a, E>, slice::IterMut<'a, E>>,
+}
+
+impl<'a, E> Iterator for StepElements<'a, E>
+{
+    type Item = (&'a E, &'a [E], &'a mut E);
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        let read = self.read;
+        self.elements.next().map(|(old, new)| (old, read, new))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>)
+    {
+        self.elements.size_hint()
+    }
+}
+
+impl<'a, E> DoubleEndedIterator for StepElements<'a, E>
+{
+    fn next_back(&mut self) -> Option<Self::Item>
+    {
+        let read = self.read;
+        self.elements.next_back().map(|(old, new)| (old, read, new))
+    }
+}
+
+impl<'a, E> ExactSizeIterator for StepElements<'a, E> {}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn order_does_not_matter()
+    {
+        // a 1D blur, which would smear across the whole row if it read values
+        // it had just written
+        let mut forwards = DoubleBuffered::new(vec![0, 0, 9, 0, 0]);
+        let mut backwards = DoubleBuffered::new(vec![0, 0, 9, 0, 0]);
+
+        let blur = |(i, (_, all, new)): (usize, (&i32, &[i32], &mut i32))|
+        {
+            let left = if i > 0 { all[i - 1] } else { 0 };
+            let right = all.get(i + 1).cloned().unwrap_or(0);
+            *new = (left + all[i] + right) / 3;
+        };
+
+        forwards.step_elements().enumerate().for_each(blur);
+        backwards.step_elements().enumerate().rev().for_each(blur);
+        assert!(forwards.has_pending_changes());
+
+        forwards.update();
+        backwards.update();
+        assert!(*forwards == [0, 3, 3, 3, 0]);
+        assert!(*forwards == *backwards);
+    }
+
+    #[test]
+    fn mismatched_lengths()
+    {
+        let mut db = DoubleBuffered::new(vec![1, 2, 3]);
+        db.pop();
+        assert!(db.step_elements().len() == 2);
+
+        db.step(|old, new| new.extend_from_slice(&old[2..]));
+        db.update();
+        assert!(*db == [1, 2, 3]);
+    }
+}
@@ -0,0 +1,179 @@
+//! Strategies for committing the write buffer to the read buffer.
+//!
+//! `DoubleBuffered` takes a second type parameter which decides what `update`
+//! actually does. The default is `CloneCommit`, which is what `update` has
+//! always done. The others trade generality for speed:
+//!
+//! | Strategy          | Requires | Write buffer after `update`      |
+//! |-------------------|----------|----------------------------------|
+//! | `CloneCommit`     | `Clone`  | unchanged                        |
+//! | `CloneFromCommit` | `Clone`  | unchanged                        |
+//! | `CopyCommit`      | `Copy`   | unchanged                        |
+//! | `SwapCommit`      | nothing  | previous contents of read buffer |
+//!
+//! `CloneFromCommit` uses `Clone::clone_from`, which lets types like `Vec`,
+//! `String` and `HashMap` reuse the read buffer's allocation instead of
+//! building a fresh one every frame.
+//!
+//! ```rust
+//! use dubble::DoubleBuffered;
+//! use dubble::strategy::CloneFromCommit;
+//!
+//! let mut my_buf = DoubleBuffered::new(Vec::new())
+//!     .with_strategy::<CloneFromCommit>();
+//!
+//! my_buf.push(1);
+//! my_buf.update();
+//! assert!(*my_buf == [1]);
+//! ```
+//!
+//! Constructors like `new` and `default` create buffers with the default
+//! strategy; use `with_strategy` to switch strategy on an existing buffer.
+//!
+//! ```rust
+//! use dubble::DoubleBuffered;
+//! use dubble::strategy::CopyCommit;
+//!
+//! let mut my_buf = DoubleBuffered::new(0u8).with_strategy::<CopyCommit>();
+//! *my_buf = 3;
+//! my_buf.update();
+//! assert!(*my_buf == 3);
+//! ```
+
+use std::mem;
+
+/// Decides how `DoubleBuffered::update` moves the contents of the write
+/// buffer into the read buffer.
+///
+/// Implement this yourself if none of the built-in strategies suit your type.
+pub trait CommitStrategy<T>
+{
+    /// Whether `commit` swaps the buffers, leaving `write` holding the old
+    /// contents of `read`. Buffers keeping their previous state need to know,
+    /// since then the old read buffer can't simply be moved into it.
+    const SWAPS: bool = false;
+
+    /// Makes `read` reflect the contents of `write`.
+    fn commit(read: &mut T, write: &mut T);
+}
+
+/// Commits by replacing the read buffer with a fresh clone of the write
+/// buffer. This is the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CloneCommit;
+
+impl<T: Clone> CommitStrategy<T> for CloneCommit
+{
+    fn commit(read: &mut T, write: &mut T)
+    {
+        *read = write.clone();
+    }
+}
+
+/// Commits using `Clone::clone_from`, which allows the read buffer to reuse
+/// its existing allocations.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CloneFromCommit;
+
+impl<T: Clone> CommitStrategy<T> for CloneFromCommit
+{
+    fn commit(read: &mut T, write: &mut T)
+    {
+        read.clone_from(write);
+    }
+}
+
+/// Commits by swapping the buffers. Nothing is cloned, but afterwards the
+/// write buffer holds whatever was previously in the read buffer.
+///
+/// For a buffer created `with_previous`, that old read buffer is also cloned
+/// into the previous state.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SwapCommit;
+
+impl<T> CommitStrategy<T> for SwapCommit
+{
+    const SWAPS: bool = true;
+
+    fn commit(read: &mut T, write: &mut T)
+    {
+        mem::swap(read, write);
+    }
+}
+
+/// Commits with a plain copy, for `Copy` types.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CopyCommit;
+
+impl<T: Copy> CommitStrategy<T> for CopyCommit
+{
+    fn commit(read: &mut T, write: &mut T)
+    {
+        *read = *write;
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use DoubleBuffered;
+
+    #[test]
+    fn clone_from_keeps_capacity()
+    {
+        let mut db = DoubleBuffered::<Vec<i32>>::default()
+            .with_strategy::<CloneFromCommit>();
+
+        db.write().extend(0..100);
+        db.update();
+        let ptr = db.read().as_ptr();
+
+        db.write().truncate(10);
+        db.update();
+
+        // the read buffer should have been reused, not reallocated
+        assert!(*db == (0..10).collect::<Vec<_>>()[..]);
+        assert!(db.read().as_ptr() == ptr);
+        assert!(db.read().capacity() >= 100);
+    }
+
+    #[test]
+    fn swap_does_not_need_clone()
+    {
+        struct NotClone(i32);
+
+        let mut db = DoubleBuffered::construct_with(|| NotClone(0))
+            .with_strategy::<SwapCommit>();
+
+        db.write().0 = 5;
+        db.update();
+        assert!(db.read().0 == 5);
+        assert!(db.write().0 == 0);
+    }
+
+    #[test]
+    fn swap_keeps_previous()
+    {
+        let mut db = DoubleBuffered::new(0).with_previous().with_strategy::<SwapCommit>();
+        *db = 1;
+        db.update();
+        *db = 2;
+        db.update();
+
+        assert!(*db == 2);
+        assert!(db.previous() == Some(&1));
+        assert!(*db.write() == 1);
+    }
+
+    #[test]
+    fn copy()
+    {
+        let mut db = DoubleBuffered::new([0u8; 4]).with_strategy::<CopyCommit>();
+        db[2] = 7;
+        assert!(*db == [0, 0, 0, 0]);
+        db.update();
+        assert!(*db == [0, 0, 7, 0]);
+        assert!(*db.write() == [0, 0, 7, 0]);
+    }
+}
@@ -0,0 +1,320 @@
+//! A double-buffer which can be read from other threads.
+//!
+//! `DoubleBuffered` can't be read by other threads while its owner is writing
+//! to it, short of wrapping it in a `Mutex`. This module provides a variant
+//! which is split into a single `WriteHandle` and any number of `ReadHandle`s,
+//! in the style of the left-right algorithm.
+//!
+//! Readers always see the most recently published buffer, and never block.
+//! The writer has the other buffer to itself; when it publishes, the two
+//! buffers trade places and the writer waits for any readers still looking at
+//! the old buffer to finish before it is allowed to write to it again.
+//!
+//! ```rust
+//! use std::thread;
+//!
+//! let (mut writer, reader) = dubble::sync::new(0);
+//!
+//! let handle = thread::spawn(move ||
+//! {
+//!     // always sees a fully published value
+//!     let value = *reader.read();
+//!     assert!(value == 0 || value == 1);
+//! });
+//!
+//! *writer.write() = 1;
+//! writer.update();
+//! assert!(*writer.read() == 1);
+//!
+//! handle.join().unwrap();
+//! ```
+
+use std::cell::UnsafeCell;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::
+{
+    AtomicUsize,
+    Ordering
+};
+use std::thread;
+
+use DoubleBuffered;
+
+struct Inner<T>
+{
+    bufs: [UnsafeCell<T>; 2],
+
+    /// Index of the buffer readers should use.
+    active: AtomicUsize,
+
+    /// Number of readers currently looking at each buffer.
+    readers: [AtomicUsize; 2],
+}
+
+// Readers share `&T` across threads, and the writer mutates the inactive
+// buffer from its own thread. Access to each buffer is coordinated through
+// `active` and `readers`.
+unsafe impl<T: Send + Sync> Sync for Inner<T> {}
+
+impl<T> Inner<T>
+{
+    fn read(&self) -> ReadGuard<'_, T>
+    {
+        loop
+        {
+            let idx = self.active.load(Ordering::SeqCst);
+            self.readers[idx].fetch_add(1, Ordering::SeqCst);
+
+            // if the writer swapped buffers before we registered, it may
+            // already be writing to this one, so try again
+            if self.active.load(Ordering::SeqCst) == idx
+            {
+                return ReadGuard { inner: self, idx };
+            }
+
+            self.readers[idx].fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Creates a new thread-safe double-buffer, with both buffers initialised to
+/// `value`. Returns the write handle and a first read handle; more read
+/// handles can be made by cloning it, or with `WriteHandle::reader`.
+pub fn new<T: Clone>(value: T) -> (WriteHandle<T>, ReadHandle<T>)
+{
+    from_buffers(value.clone(), value)
+}
+
+fn from_buffers<T>(rbuf: T, wbuf: T) -> (WriteHandle<T>, ReadHandle<T>)
+{
+    let inner = Arc::new(Inner
+    {
+        bufs: [UnsafeCell::new(rbuf), UnsafeCell::new(wbuf)],
+        active: AtomicUsize::new(0),
+        readers: [AtomicUsize::new(0), AtomicUsize::new(0)],
+    });
+
+    let reader = ReadHandle { inner: inner.clone() };
+    (WriteHandle { inner }, reader)
+}
+
+impl<T, S> DoubleBuffered<T, S>
+{
+    /// Converts this into a thread-safe double-buffer, keeping the contents
+    /// of both buffers. See the `sync` module for details.
+    ///
+    /// ```rust
+    /// # use dubble::DoubleBuffered;
+    /// let mut my_buf = DoubleBuffered::new(1);
+    /// *my_buf.write() = 2;
+    ///
+    /// let (mut writer, reader) = my_buf.split();
+    /// assert!(*reader.read() == 1);
+    ///
+    /// writer.update();
+    /// assert!(*reader.read() == 2);
+    /// ```
+    pub fn split(self) -> (WriteHandle<T>, ReadHandle<T>)
+    {
+        let (rbuf, wbuf) = self.into_buffers();
+        from_buffers(rbuf, wbuf)
+    }
+}
+
+/// The writing half of a thread-safe double-buffer. There is only ever one of
+/// these per buffer.
+pub struct WriteHandle<T>
+{
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> WriteHandle<T>
+{
+    fn active(&self) -> usize
+    {
+        // only the writer changes `active`, so this can't change under us
+        self.inner.active.load(Ordering::SeqCst)
+    }
+
+    /// Returns an immutable reference to the published buffer; the same one
+    /// readers are seeing.
+    pub fn read(&self) -> &T
+    {
+        // readers only ever take shared references to the active buffer, and
+        // the writer only mutates the inactive one
+        unsafe { &*self.inner.bufs[self.active()].get() }
+    }
+
+    /// Returns a mutable reference to the write buffer. Changes made through
+    /// this reference are not visible to readers until the next `publish` or
+    /// `update`.
+    pub fn write(&mut self) -> &mut T
+    {
+        // `publish` waits for readers to leave the buffer before making it the
+        // write buffer, and readers never enter the inactive buffer
+        unsafe { &mut *self.inner.bufs[1 - self.active()].get() }
+    }
+
+    /// Publishes the write buffer to readers by swapping the two buffers.
+    ///
+    /// This waits until no reader is still using the previously published
+    /// buffer, so it never returns if a `ReadGuard` is alive on this thread.
+    /// Afterwards the write buffer holds the *previously* published
+    /// value, in the same way as `DoubleBuffered::update_swap`.
+    pub fn publish(&mut self)
+    {
+        let old = self.active();
+        self.inner.active.store(1 - old, Ordering::SeqCst);
+
+        while self.inner.readers[old].load(Ordering::SeqCst) != 0
+        {
+            thread::yield_now();
+        }
+    }
+
+    /// Creates a new read handle for this buffer.
+    pub fn reader(&self) -> ReadHandle<T>
+    {
+        ReadHandle { inner: self.inner.clone() }
+    }
+}
+
+impl<T: Clone> WriteHandle<T>
+{
+    /// Publishes the write buffer, and then brings the new write buffer up to
+    /// date with it, so that it behaves like `DoubleBuffered::update`.
+    pub fn update(&mut self)
+    {
+        self.publish();
+
+        let active = self.active();
+        let (rbuf, wbuf) = unsafe
+        {
+            (&*self.inner.bufs[active].get(), &mut *self.inner.bufs[1 - active].get())
+        };
+
+        wbuf.clone_from(rbuf);
+    }
+}
+
+/// A reading half of a thread-safe double-buffer. These can be cloned and
+/// sent to other threads freely.
+pub struct ReadHandle<T>
+{
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> ReadHandle<T>
+{
+    /// Returns a guard which dereferences to the published buffer.
+    ///
+    /// This never blocks, but for as long as the guard is alive, the writer's
+    /// next `publish` (or `update`) blocks until it's dropped, so don't hang
+    /// on to it. Holding a guard on the writer's own thread while publishing
+    /// deadlocks.
+    pub fn read(&self) -> ReadGuard<'_, T>
+    {
+        self.inner.read()
+    }
+}
+
+impl<T> Clone for ReadHandle<T>
+{
+    fn clone(&self) -> Self
+    {
+        ReadHandle { inner: self.inner.clone() }
+    }
+}
+
+/// A reference to the published buffer of a thread-safe double-buffer,
+/// obtained from `ReadHandle::read`.
+pub struct ReadGuard<'a, T: 'a>
+{
+    inner: &'a Inner<T>,
+    idx: usize,
+}
+
+impl<'a, T> Deref for ReadGuard<'a, T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &T
+    {
+        // the writer won't touch this buffer until our count is released
+        unsafe { &*self.inner.bufs[self.idx].get() }
+    }
+}
+
+impl<'a, T> Drop for ReadGuard<'a, T>
+{
+    fn drop(&mut self)
+    {
+        self.inner.readers[self.idx].fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn basic()
+    {
+        let (mut w, r) = new(0);
+
+        *w.write() = 1;
+        assert!(*r.read() == 0);
+        assert!(*w.read() == 0);
+
+        w.update();
+        assert!(*r.read() == 1);
+        assert!(*w.write() == 1);
+
+        *w.write() = 2;
+        w.publish();
+        assert!(*r.read() == 2);
+        assert!(*w.write() == 1);
+    }
+
+    #[test]
+    fn readers_see_whole_values()
+    {
+        const ITERS: u64 = 10_000;
+
+        let (mut w, r) = new((0u64, 0u64));
+
+        let readers: Vec<_> = (0..4).map(|_|
+        {
+            let r = r.clone();
+            thread::spawn(move ||
+            {
+                let mut last = 0;
+                loop
+                {
+                    let (a, b) = *r.read();
+                    assert!(b == a * 2);
+                    assert!(a >= last);
+                    last = a;
+
+                    if a == ITERS
+                    {
+                        break;
+                    }
+                }
+            })
+        }).collect();
+
+        for i in 1..=ITERS
+        {
+            *w.write() = (i, i * 2);
+            w.update();
+        }
+
+        for reader in readers
+        {
+            reader.join().unwrap();
+        }
+    }
+}
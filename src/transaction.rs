@@ -0,0 +1,181 @@
+//! Scoped writes which are either committed or rolled back.
+
+use std::ops::
+{
+    Deref,
+    DerefMut
+};
+
+use DoubleBuffered;
+use strategy::CommitStrategy;
+
+impl<T: Clone, S: CommitStrategy<T>> DoubleBuffered<T, S>
+{
+    /// Starts a transaction on the write buffer.
+    ///
+    /// The returned guard dereferences to the write buffer. Calling `commit`
+    /// on it updates the buffer; if it is dropped without being committed,
+    /// including while unwinding from a panic, the write buffer is restored
+    /// to what it held when the transaction started, so that a half-finished
+    /// frame is never published. Writes made before the transaction are
+    /// kept either way.
+    ///
+    /// Starting a transaction clones the write buffer, to have something to
+    /// restore.
+    ///
+    /// ```rust
+    /// # use dubble::DoubleBuffered;
+    /// let mut my_buf = DoubleBuffered::new(vec![1, 2]);
+    ///
+    /// {
+    ///     let mut tx = my_buf.transaction();
+    ///     tx.push(3);
+    ///     tx.commit();
+    /// }
+    /// assert!(*my_buf == [1, 2, 3]);
+    ///
+    /// {
+    ///     let mut tx = my_buf.transaction();
+    ///     tx.clear();
+    ///     // dropped without committing
+    /// }
+    /// assert!(*my_buf == [1, 2, 3]);
+    /// assert!(*my_buf.write() == [1, 2, 3]);
+    /// ```
+    pub fn transaction(&mut self) -> Transaction<'_, T, S>
+    {
+        let saved = Some(self.wbuf.clone());
+        let dirty = self.dirty;
+        Transaction { buf: self, saved, dirty }
+    }
+}
+
+/// A pending set of writes to a `DoubleBuffered`, created by
+/// `DoubleBuffered::transaction`.
+#[must_use = "dropping a transaction without committing it discards its writes"]
+pub struct Transaction<'a, T: Clone + 'a, S: CommitStrategy<T> + 'a>
+{
+    buf: &'a mut DoubleBuffered<T, S>,
+
+    /// The write buffer and its dirty flag as they were when the transaction
+    /// started, or `None` once it has been committed.
+    saved: Option<T>,
+    dirty: bool,
+}
+
+impl<'a, T: Clone, S: CommitStrategy<T>> Transaction<'a, T, S>
+{
+    /// Returns an immutable reference to the read buffer, which is not
+    /// affected by the transaction until it is committed.
+    pub fn read(&self) -> &T
+    {
+        self.buf.read()
+    }
+
+    /// Commits the writes made during the transaction.
+    pub fn commit(mut self)
+    {
+        self.saved = None;
+        self.buf.update();
+    }
+
+    /// Discards the writes made during the transaction. This is the same as
+    /// dropping it.
+    pub fn rollback(self)
+    {
+        // restoring happens in `drop`
+    }
+}
+
+impl<'a, T: Clone, S: CommitStrategy<T>> Deref for Transaction<'a, T, S>
+{
+    type Target = T;
+
+    fn deref(&self) -> &T
+    {
+        &self.buf.wbuf
+    }
+}
+
+impl<'a, T: Clone, S: CommitStrategy<T>> DerefMut for Transaction<'a, T, S>
+{
+    fn deref_mut(&mut self) -> &mut T
+    {
+        self.buf.write()
+    }
+}
+
+impl<'a, T: Clone, S: CommitStrategy<T>> Drop for Transaction<'a, T, S>
+{
+    fn drop(&mut self)
+    {
+        if let Some(saved) = self.saved.take()
+        {
+            self.buf.wbuf = saved;
+            self.buf.dirty = self.dirty;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::panic;
+
+    #[test]
+    fn commit_and_rollback()
+    {
+        let mut db = DoubleBuffered::new(1);
+
+        let mut tx = db.transaction();
+        *tx = 2;
+        assert!(*tx.read() == 1);
+        tx.commit();
+        assert!(*db == 2);
+
+        let mut tx = db.transaction();
+        *tx = 3;
+        tx.rollback();
+        assert!(*db == 2);
+        assert!(*db.write() == 2);
+    }
+
+    #[test]
+    fn panic_rolls_back()
+    {
+        let mut db = DoubleBuffered::new(vec![1, 2, 3]);
+
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(||
+        {
+            let mut tx = db.transaction();
+            tx[0] = 100;
+            panic!("simulation blew up");
+        }));
+
+        assert!(result.is_err());
+
+        // the half-written frame is gone, so the next update is harmless
+        db.update();
+        assert!(*db == [1, 2, 3]);
+    }
+
+    #[test]
+    fn earlier_writes_survive_rollback()
+    {
+        let mut db = DoubleBuffered::new(1);
+        *db = 5;
+
+        {
+            let mut tx = db.transaction();
+            *tx = 6;
+        }
+
+        assert!(*db == 1);
+        assert!(db.has_pending_changes());
+        assert!(*db.write() == 5);
+
+        db.update();
+        assert!(*db == 5);
+    }
+}
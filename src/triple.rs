@@ -0,0 +1,291 @@
+//! A triple-buffer, for handing frames from one thread to another.
+//!
+//! With a double-buffer, the reader and the writer have to take turns: the
+//! writer can't commit while the reader is looking at the read buffer. A
+//! triple-buffer adds a third buffer in the middle, so that the writer always
+//! has somewhere to write and the reader always has a complete frame to read.
+//! Neither side ever waits for the other; if the writer commits several
+//! times between reads, the reader simply skips to the newest frame.
+//!
+//! ```rust
+//! use std::thread;
+//! use dubble::TripleBuffered;
+//!
+//! let (mut sim, mut render) = TripleBuffered::new(0).split();
+//!
+//! let sim = thread::spawn(move ||
+//! {
+//!     for frame in 1..=100
+//!     {
+//!         *sim.write() = frame;
+//!         sim.update();
+//!     }
+//! });
+//!
+//! // always the newest complete frame, never a partially written one
+//! let frame = *render.read();
+//! assert!(frame <= 100);
+//!
+//! sim.join().unwrap();
+//! assert!(*render.read() == 100);
+//! ```
+
+use std::cell::UnsafeCell;
+use std::sync::Arc;
+use std::sync::atomic::
+{
+    AtomicUsize,
+    Ordering
+};
+
+/// Set in `Shared::middle` when the middle buffer holds a frame the reader
+/// hasn't picked up yet.
+const FRESH: usize = 0b100;
+const INDEX: usize = 0b011;
+
+struct Shared<T>
+{
+    bufs: [UnsafeCell<T>; 3],
+
+    /// Index of the middle buffer, plus the `FRESH` flag.
+    middle: AtomicUsize,
+}
+
+// At any moment each buffer is owned by exactly one of the writer, the reader,
+// or the middle slot; ownership only changes hands through `middle`.
+unsafe impl<T: Send + Sync> Sync for Shared<T> {}
+
+/// A triple-buffered value. Use `split` to get the halves for each thread.
+///
+/// It can also be used without splitting it, in which case it behaves much
+/// like a `DoubleBuffered`, except that `read` needs `&mut self`, since
+/// reading may pick up a newer frame.
+pub struct TripleBuffered<T>
+{
+    writer: WriteHandle<T>,
+    reader: ReadHandle<T>,
+}
+
+impl<T: Clone> TripleBuffered<T>
+{
+    /// Initialises all three buffers with `value`.
+    pub fn new(value: T) -> Self
+    {
+        Self::construct_with(|| value.clone())
+    }
+}
+
+impl<T> TripleBuffered<T>
+{
+    /// Uses `constructor` to construct each buffer.
+    pub fn construct_with<F: Fn() -> T>(constructor: F) -> Self
+    {
+        let shared = Arc::new(Shared
+        {
+            bufs: [
+                UnsafeCell::new(constructor()),
+                UnsafeCell::new(constructor()),
+                UnsafeCell::new(constructor()),
+            ],
+            middle: AtomicUsize::new(1),
+        });
+
+        Self
+        {
+            writer: WriteHandle { shared: shared.clone(), back: 0 },
+            reader: ReadHandle { shared, front: 2 },
+        }
+    }
+
+    /// Returns a reference to the newest committed frame.
+    pub fn read(&mut self) -> &T
+    {
+        self.reader.read()
+    }
+
+    /// Returns a mutable reference to the write buffer.
+    pub fn write(&mut self) -> &mut T
+    {
+        self.writer.write()
+    }
+
+    /// Commits the write buffer without re-syncing it; see
+    /// `WriteHandle::publish`.
+    pub fn publish(&mut self)
+    {
+        self.writer.publish();
+    }
+
+    /// Splits the buffer into its writing and reading halves, which can be
+    /// sent to different threads.
+    pub fn split(self) -> (WriteHandle<T>, ReadHandle<T>)
+    {
+        (self.writer, self.reader)
+    }
+}
+
+impl<T: Clone> TripleBuffered<T>
+{
+    /// Commits the write buffer; see `WriteHandle::update`.
+    pub fn update(&mut self)
+    {
+        self.writer.update();
+    }
+}
+
+impl<T: Default> Default for TripleBuffered<T>
+{
+    fn default() -> Self
+    {
+        Self::construct_with(T::default)
+    }
+}
+
+/// The writing half of a `TripleBuffered`.
+pub struct WriteHandle<T>
+{
+    shared: Arc<Shared<T>>,
+    back: usize,
+}
+
+impl<T> WriteHandle<T>
+{
+    /// Returns a mutable reference to the write buffer.
+    pub fn write(&mut self) -> &mut T
+    {
+        // the back buffer is only ever accessed by the writer
+        unsafe { &mut *self.shared.bufs[self.back].get() }
+    }
+
+    /// Makes the write buffer available to the reader, without waiting.
+    ///
+    /// The writer gets the old middle buffer in exchange, so afterwards the
+    /// write buffer holds some older frame rather than the one just
+    /// committed.
+    pub fn publish(&mut self)
+    {
+        let old = self.shared.middle.swap(self.back | FRESH, Ordering::AcqRel);
+        self.back = old & INDEX;
+    }
+}
+
+impl<T: Clone> WriteHandle<T>
+{
+    /// Makes the write buffer available to the reader, and then brings the
+    /// new write buffer up to date with it, so that it behaves like
+    /// `DoubleBuffered::update`.
+    pub fn update(&mut self)
+    {
+        let committed = self.back;
+        self.publish();
+
+        // the reader may pick up the committed buffer at any point, but it
+        // only ever reads from it, and can't hand it back to us until we
+        // publish again
+        let (src, dst) = unsafe
+        {
+            (&*self.shared.bufs[committed].get(), &mut *self.shared.bufs[self.back].get())
+        };
+
+        dst.clone_from(src);
+    }
+}
+
+/// The reading half of a `TripleBuffered`.
+pub struct ReadHandle<T>
+{
+    shared: Arc<Shared<T>>,
+    front: usize,
+}
+
+impl<T> ReadHandle<T>
+{
+    /// Returns a reference to the newest committed frame.
+    pub fn read(&mut self) -> &T
+    {
+        if self.has_new()
+        {
+            let old = self.shared.middle.swap(self.front, Ordering::AcqRel);
+            self.front = old & INDEX;
+        }
+
+        // the front buffer is only ever written by the writer before it was
+        // published, which happens-before the swap above
+        unsafe { &*self.shared.bufs[self.front].get() }
+    }
+
+    /// Returns whether a frame has been committed since the last `read`.
+    pub fn has_new(&self) -> bool
+    {
+        self.shared.middle.load(Ordering::Acquire) & FRESH != 0
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn basic()
+    {
+        let mut tb = TripleBuffered::new(0);
+
+        *tb.write() = 1;
+        assert!(*tb.read() == 0);
+
+        tb.update();
+        assert!(*tb.write() == 1);
+        assert!(*tb.read() == 1);
+    }
+
+    #[test]
+    fn reader_skips_to_newest()
+    {
+        let (mut w, mut r) = TripleBuffered::new(0).split();
+
+        for i in 1..=5
+        {
+            *w.write() = i;
+            w.publish();
+        }
+
+        assert!(r.has_new());
+        assert!(*r.read() == 5);
+        assert!(!r.has_new());
+        assert!(*r.read() == 5);
+    }
+
+    #[test]
+    fn readers_see_whole_frames()
+    {
+        const FRAMES: u64 = 10_000;
+
+        let (mut w, mut r) = TripleBuffered::new(vec![0u64; 64]).split();
+
+        let reader = thread::spawn(move ||
+        {
+            let mut last = 0;
+            while last != FRAMES
+            {
+                let frame = r.read();
+                assert!(frame.iter().all(|&x| x == frame[0]));
+                assert!(frame[0] >= last);
+                last = frame[0];
+            }
+        });
+
+        for i in 1..=FRAMES
+        {
+            for x in w.write().iter_mut()
+            {
+                *x = i;
+            }
+
+            w.update();
+        }
+
+        reader.join().unwrap();
+    }
+}
@@ -0,0 +1,385 @@
+//! Committing several buffers at once.
+//!
+//! Forgetting to `update` one of many buffers leads to values which lag a
+//! frame behind, which is hard to track down. `Updatable` is implemented by
+//! every buffer in this crate, as well as by tuples, arrays, `Vec`s and
+//! `Option`s of them, so that a whole group of buffers can be committed in one
+//! call. `BufferSet` collects buffers of different types together.
+//!
+//! ```rust
+//! use dubble::{BufferSet, DoubleBuffered, Updatable};
+//!
+//! let mut health = DoubleBuffered::new(100);
+//! let mut names = DoubleBuffered::new(vec!["alice"]);
+//!
+//! *health -= 10;
+//! names.push("bob");
+//!
+//! BufferSet::new()
+//!     .with(&mut health)
+//!     .with(&mut names)
+//!     .update();
+//!
+//! assert!(*health == 90);
+//! assert!(*names == ["alice", "bob"]);
+//!
+//! // tuples work as well
+//! *health -= 10;
+//! (&mut health, &mut names).update();
+//! assert!(*health == 80);
+//! ```
+
+use std::hash::Hash;
+
+use AccumulatingBuffered;
+use DirtyVec;
+use DoubleBuffered;
+use DoubleBufferedGrid;
+use DoubleBufferedMap;
+use DoubleBufferedVec;
+use LazyBuffered;
+use Monoid;
+use MultiBuffered;
+use OverlayBuffered;
+use SeqDoubleBuffered;
+use SnapshotBuffered;
+use TripleBuffered;
+use strategy::CommitStrategy;
+use sync;
+use triple;
+
+/// Something which can commit its pending writes.
+pub trait Updatable
+{
+    /// Commits pending writes, so that they become visible to readers.
+    fn update(&mut self);
+}
+
+impl<T, S: CommitStrategy<T>> Updatable for DoubleBuffered<T, S>
+{
+    fn update(&mut self)
+    {
+        DoubleBuffered::update(self);
+    }
+}
+
+impl<T: Clone> Updatable for DirtyVec<T>
+{
+    fn update(&mut self)
+    {
+        DirtyVec::update(self);
+    }
+}
+
+impl<T: Clone, const D: usize> Updatable for DoubleBufferedGrid<T, D>
+{
+    fn update(&mut self)
+    {
+        DoubleBufferedGrid::update(self);
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Updatable for DoubleBufferedMap<K, V>
+{
+    fn update(&mut self)
+    {
+        DoubleBufferedMap::update(self);
+    }
+}
+
+impl<T: Clone> Updatable for DoubleBufferedVec<T>
+{
+    fn update(&mut self)
+    {
+        DoubleBufferedVec::update(self);
+    }
+}
+
+impl<T: Monoid> Updatable for AccumulatingBuffered<T>
+{
+    fn update(&mut self)
+    {
+        AccumulatingBuffered::update(self);
+    }
+}
+
+impl<T: Clone> Updatable for LazyBuffered<T>
+{
+    fn update(&mut self)
+    {
+        LazyBuffered::update(self);
+    }
+}
+
+impl<T: Clone> Updatable for OverlayBuffered<T>
+{
+    fn update(&mut self)
+    {
+        OverlayBuffered::update(self);
+    }
+}
+
+impl<T: Clone, const N: usize> Updatable for MultiBuffered<T, N>
+{
+    fn update(&mut self)
+    {
+        MultiBuffered::update(self);
+    }
+}
+
+impl<T: Clone> Updatable for SnapshotBuffered<T>
+{
+    fn update(&mut self)
+    {
+        SnapshotBuffered::update(self);
+    }
+}
+
+impl<T: Copy> Updatable for SeqDoubleBuffered<T>
+{
+    fn update(&mut self)
+    {
+        SeqDoubleBuffered::update(self);
+    }
+}
+
+impl<T: Clone> Updatable for TripleBuffered<T>
+{
+    fn update(&mut self)
+    {
+        TripleBuffered::update(self);
+    }
+}
+
+impl<T: Clone> Updatable for triple::WriteHandle<T>
+{
+    fn update(&mut self)
+    {
+        triple::WriteHandle::update(self);
+    }
+}
+
+impl<T: Clone> Updatable for sync::WriteHandle<T>
+{
+    fn update(&mut self)
+    {
+        sync::WriteHandle::update(self);
+    }
+}
+
+impl<U: Updatable + ?Sized> Updatable for &mut U
+{
+    fn update(&mut self)
+    {
+        (**self).update();
+    }
+}
+
+impl<U: Updatable + ?Sized> Updatable for Box<U>
+{
+    fn update(&mut self)
+    {
+        (**self).update();
+    }
+}
+
+impl<U: Updatable> Updatable for Option<U>
+{
+    fn update(&mut self)
+    {
+        if let Some(u) = self
+        {
+            u.update();
+        }
+    }
+}
+
+impl<U: Updatable> Updatable for [U]
+{
+    fn update(&mut self)
+    {
+        for u in self
+        {
+            u.update();
+        }
+    }
+}
+
+impl<U: Updatable, const N: usize> Updatable for [U; N]
+{
+    fn update(&mut self)
+    {
+        self[..].update();
+    }
+}
+
+impl<U: Updatable> Updatable for Vec<U>
+{
+    fn update(&mut self)
+    {
+        self[..].update();
+    }
+}
+
+macro_rules! impl_updatable_tuple
+{
+    ($($name:ident)+) =>
+    {
+        impl<$($name: Updatable),+> Updatable for ($($name,)+)
+        {
+            #[allow(non_snake_case)]
+            fn update(&mut self)
+            {
+                let ($(ref mut $name,)+) = *self;
+                $($name.update();)+
+            }
+        }
+    };
+}
+
+impl_updatable_tuple!(A);
+impl_updatable_tuple!(A B);
+impl_updatable_tuple!(A B C);
+impl_updatable_tuple!(A B C D);
+impl_updatable_tuple!(A B C D E);
+impl_updatable_tuple!(A B C D E F);
+impl_updatable_tuple!(A B C D E F G);
+impl_updatable_tuple!(A B C D E F G H);
+impl_updatable_tuple!(A B C D E F G H I);
+impl_updatable_tuple!(A B C D E F G H I J);
+impl_updatable_tuple!(A B C D E F G H I J K);
+impl_updatable_tuple!(A B C D E F G H I J K L);
+
+/// A collection of buffers, possibly of different types, which are all
+/// committed together by `update`.
+///
+/// It can hold either borrowed buffers (`&mut DoubleBuffered<T>` and so on)
+/// or owned ones, and is itself `Updatable`, so sets can be nested.
+#[derive(Default)]
+pub struct BufferSet<'a>
+{
+    buffers: Vec<Box<dyn Updatable + 'a>>,
+}
+
+impl<'a> BufferSet<'a>
+{
+    /// Creates an empty set.
+    pub fn new() -> Self
+    {
+        Self { buffers: Vec::new() }
+    }
+
+    /// Adds a buffer to the set.
+    pub fn push<U: Updatable + 'a>(&mut self, buffer: U)
+    {
+        self.buffers.push(Box::new(buffer));
+    }
+
+    /// Adds a buffer to the set, builder-style.
+    pub fn with<U: Updatable + 'a>(mut self, buffer: U) -> Self
+    {
+        self.push(buffer);
+        self
+    }
+
+    /// Returns the number of buffers in the set.
+    pub fn len(&self) -> usize
+    {
+        self.buffers.len()
+    }
+
+    /// Returns whether the set is empty.
+    pub fn is_empty(&self) -> bool
+    {
+        self.buffers.is_empty()
+    }
+
+    /// Commits every buffer in the set, in the order they were added.
+    pub fn update(&mut self)
+    {
+        self.buffers.update();
+    }
+}
+
+impl<'a> Updatable for BufferSet<'a>
+{
+    fn update(&mut self)
+    {
+        BufferSet::update(self);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    struct World
+    {
+        positions: DoubleBuffered<Vec<i32>>,
+        velocities: DirtyVec<i32>,
+        ticks: MultiBuffered<u32, 2>,
+    }
+
+    impl Updatable for World
+    {
+        fn update(&mut self)
+        {
+            (&mut self.positions, &mut self.velocities, &mut self.ticks).update();
+        }
+    }
+
+    #[test]
+    fn heterogeneous_set()
+    {
+        let mut a = DoubleBuffered::new(1);
+        let mut b = DoubleBuffered::new(String::new());
+        let mut c: Vec<_> = (0..3).map(|_| DoubleBuffered::new(0u8)).collect();
+
+        *a = 2;
+        b.push('x');
+        c[1].upsert(0);
+        *c[2] = 5;
+
+        {
+            let mut set = BufferSet::new();
+            set.push(&mut a);
+            set.push(&mut b);
+            set.push(&mut c);
+            assert!(set.len() == 3);
+            set.update();
+        }
+
+        assert!(*a == 2);
+        assert!(*b == "x");
+        assert!(*c[2] == 5);
+    }
+
+    #[test]
+    fn nested()
+    {
+        let mut world = World
+        {
+            positions: DoubleBuffered::new(vec![0; 2]),
+            velocities: DirtyVec::new(vec![0; 2]),
+            ticks: MultiBuffered::new(0),
+        };
+
+        world.positions[0] = 1;
+        world.velocities[1] = 2;
+        *world.ticks = 1;
+
+        let mut score = DoubleBuffered::new(0);
+        *score = 10;
+
+        let mut set = BufferSet::new().with(&mut world);
+        set.push(BufferSet::new().with(&mut score));
+        set.update();
+        drop(set);
+
+        assert!(*world.positions == [1, 0]);
+        assert!(*world.velocities == [0, 2]);
+        assert!(*world.ticks == 1);
+        assert!(*score == 10);
+    }
+}